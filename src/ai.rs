@@ -12,12 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub(crate) mod expectimax;
 pub(crate) mod mcts;
 pub(crate) mod minimax;
 pub(crate) mod random;
 pub(crate) mod simple;
-type Depth = i8;
-type Score = i8;
-const X_WIN: Score = 32;
-const DRAW: Score = X_WIN / 2;
-const O_WIN: Score = 0;
+pub(crate) type Depth = i16;
+pub(crate) type Score = i16;
+pub(crate) const X_WIN: Score = 256;
+pub(crate) const DRAW: Score = X_WIN / 2;
+pub(crate) const O_WIN: Score = 0;
@@ -0,0 +1,142 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::{
+  ai::{self, Depth},
+  game::{Game, Player, Result, Rules},
+  player,
+};
+
+// without pruning every value found is exact
+type Table = HashMap<u64, f32>;
+
+// minimax where chance nodes are worth the average of their outcomes weighted
+// by their odds, there is no pruning as the bounds do not hold across chance
+// nodes, and only the moves count towards the depth
+fn expectimax<R: Rules>(rules: &R, table: &mut Table, node: &R::State, depth: Depth) -> f32 {
+  let key = rules.get_key(node);
+
+  if let Some(value) = key.and_then(|key| table.get(&key).copied()) {
+    return value;
+  }
+
+  let value = match rules.get_result(node) {
+    Result::Draw => ai::DRAW as f32,
+    Result::XWin => (ai::X_WIN - depth) as f32,
+    Result::OWin => (ai::O_WIN + depth) as f32,
+    Result::Continue if depth == rules.get_search_depth() => rules.evaluate(node) as f32,
+    Result::Continue => {
+      if let Some(chances) = rules.get_chances(node) {
+        chances
+          .iter()
+          .map(|(state, odds)| odds * expectimax(rules, table, state, depth))
+          .sum()
+      } else {
+        let values = rules
+          .get_candidate_moves(node)
+          .map(|m| expectimax(rules, table, &rules.apply(node, m), depth + 1));
+
+        if rules.get_player(node) == Player::X {
+          values.fold(f32::MIN, f32::max)
+        } else {
+          values.fold(f32::MAX, f32::min)
+        }
+      }
+    }
+  };
+
+  if let Some(key) = key {
+    table.insert(key, value);
+  }
+
+  value
+}
+
+pub(crate) fn search<R: Rules>(rules: &R, state: &R::State) -> R::Move {
+  let player = rules.get_player(state);
+  let mut table = Table::new();
+  let mut best_move = None;
+  let mut best_value = 0.0;
+
+  for m in rules.get_candidate_moves(state) {
+    let value = expectimax(rules, &mut table, &rules.apply(state, m), 1);
+
+    if best_move.is_none()
+      || player == Player::X && value > best_value
+      || player == Player::O && value < best_value
+    {
+      best_move = Some(m);
+      best_value = value;
+    }
+  }
+
+  best_move.unwrap()
+}
+
+pub(crate) struct Expectimax;
+
+impl<R: Rules> player::Player<R> for Expectimax {
+  fn get_name(&self) -> &'static str {
+    "expectimax"
+  }
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    search(&game.rules, &game.state)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    grid::{self, Board},
+    random_turn::{self, RandomTurn},
+  };
+
+  // x . x
+  // o . o
+  // . . .
+  // whoever the coin picks wins
+  fn get_state(player: Option<Player>) -> random_turn::State {
+    let grid = [
+      (0, Player::X),
+      (2, Player::X),
+      (3, Player::O),
+      (5, Player::O),
+    ]
+    .iter()
+    .fold(grid::EMPTY, |grid, (cell, player)| {
+      grid::mark(grid, *cell, *player)
+    });
+    (grid, 4, player)
+  }
+
+  #[test]
+  fn coin_flip_is_worth_the_average_of_its_outcomes() {
+    let rules = RandomTurn::new(Board::classic());
+    let value = expectimax(&rules, &mut Table::new(), &get_state(None), 0);
+
+    assert_eq!(value, (ai::X_WIN - 1 + ai::O_WIN + 1) as f32 / 2.0);
+  }
+
+  #[test]
+  fn side_picked_by_the_coin_wins() {
+    let rules = RandomTurn::new(Board::classic());
+
+    assert_eq!(search(&rules, &get_state(Some(Player::X))), 1);
+    assert_eq!(search(&rules, &get_state(Some(Player::O))), 4);
+  }
+}
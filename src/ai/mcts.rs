@@ -13,32 +13,60 @@
 // limitations under the License.
 
 use crate::{
-  game::{self, Game, Player, Result, Turn},
-  grid::{self, Cell, Grid},
+  game::{self, Game, Player, Result, Rules},
+  player,
 };
 const ROOT_NODE: usize = 0;
 
-struct Node {
+struct Node<S, M> {
   childrens: Vec<usize>,
   parent: usize,
   wins: f32,
   playouts: i32,
-  grid: Grid,
-  turn: Turn,
+  state: S,
+  // none for the outcomes of a chance node
+  last_move: Option<M>,
+  odds: f32,
 }
 
 fn uct(wins: f32, playouts: f32, parent_playouts: f32) -> f32 {
   wins / playouts + std::f32::consts::SQRT_2 * (parent_playouts.ln() / playouts).sqrt()
 }
 
-fn select(tree: &Vec<Node>) -> usize {
+// the children of a chance node are its outcomes instead of moves
+fn is_chance<S, M>(tree: &[Node<S, M>], node: usize) -> bool {
+  tree[tree[node].childrens[0]].last_move.is_none()
+}
+
+// outcomes are drawn by their odds instead of chosen
+fn get_random_outcome<S, M>(tree: &[Node<S, M>], node: usize) -> usize {
+  let outcomes: Vec<(usize, f32)> = tree[node]
+    .childrens
+    .iter()
+    .map(|child| (*child, tree[*child].odds))
+    .collect();
+
+  game::get_random_chance(&outcomes)
+}
+
+fn select<S, M>(tree: &[Node<S, M>]) -> usize {
   let mut leaf = ROOT_NODE;
 
   loop {
-    if tree[leaf].childrens.len() == 0 {
+    if tree[leaf].childrens.is_empty() {
       return leaf;
     }
 
+    if is_chance(tree, leaf) {
+      leaf = get_random_outcome(tree, leaf);
+
+      if tree[leaf].playouts == 0 {
+        return leaf;
+      }
+
+      continue;
+    }
+
     let mut best_score = f32::MIN;
 
     for child in &tree[leaf].childrens {
@@ -60,27 +88,40 @@ fn select(tree: &Vec<Node>) -> usize {
   }
 }
 
-fn expand(tree: &mut Vec<Node>, leaf: usize) -> usize {
-  if grid::get_result(tree[leaf].grid, tree[leaf].turn) != Result::Continue {
+fn expand<R: Rules>(rules: &R, tree: &mut Vec<Node<R::State, R::Move>>, leaf: usize) -> usize {
+  if rules.get_result(&tree[leaf].state) != Result::Continue {
     return leaf;
   }
 
-  let player = game::get_player_from_turn(tree[leaf].turn);
-  let turn = tree[leaf].turn + 1;
-
-  for cell in grid::get_empty_cells(tree[leaf].grid) {
+  let nodes: Vec<(R::State, Option<R::Move>, f32)> = match rules.get_chances(&tree[leaf].state) {
+    Some(chances) => chances
+      .into_iter()
+      .map(|(state, odds)| (state, None, odds))
+      .collect(),
+    None => rules
+      .get_candidate_moves(&tree[leaf].state)
+      .map(|m| (rules.apply(&tree[leaf].state, m), Some(m), 1.0))
+      .collect(),
+  };
+
+  for (state, last_move, odds) in nodes {
     let children = tree.len();
     tree.push(Node {
-      childrens: Vec::with_capacity(grid::NUMBER_CELLS!()),
+      childrens: Vec::new(),
       parent: leaf,
       wins: 0.0,
       playouts: 0,
-      grid: grid::mark(tree[leaf].grid, cell, player),
-      turn: turn,
+      state,
+      last_move,
+      odds,
     });
     tree[leaf].childrens.push(children);
   }
 
+  if is_chance(tree, leaf) {
+    return get_random_outcome(tree, leaf);
+  }
+
   use rand::prelude::SliceRandom;
 
   *tree[leaf]
@@ -89,48 +130,54 @@ fn expand(tree: &mut Vec<Node>, leaf: usize) -> usize {
     .unwrap()
 }
 
-fn simulate(mut grid: Grid, mut turn: Turn) -> Result {
+fn simulate<R: Rules>(rules: &R, mut state: R::State) -> Result {
   loop {
-    let result = grid::get_result(grid, turn);
+    let result = rules.get_result(&state);
 
     if result != Result::Continue {
       return result;
     }
 
-    grid = grid::mark(
-      grid,
-      grid::get_random_empty_cell(grid),
-      game::get_player_from_turn(turn),
-    );
-    turn += 1;
+    state = match rules.get_chances(&state) {
+      Some(chances) => game::get_random_chance(&chances),
+      None => rules.apply(&state, rules.get_random_move(&state)),
+    };
   }
 }
 
-fn backpropagate(tree: &mut Vec<Node>, mut child: usize, result: Result) {
+fn backpropagate<R: Rules>(
+  rules: &R,
+  tree: &mut [Node<R::State, R::Move>],
+  mut child: usize,
+  result: Result,
+) {
   loop {
+    let parent = tree[child].parent;
     tree[child].playouts += 1;
 
     if result == Result::Draw {
       tree[child].wins += 0.5;
-    } else {
-      let player = game::get_player_from_turn(tree[child].turn);
+    } else if parent != usize::MAX {
+      // the move into a node is made by the player to move in its parent,
+      // players do not always alternate
+      let player = rules.get_player(&tree[parent].state);
 
-      if result == Result::XWin && player == Player::O
-        || result == Result::OWin && player == Player::X
+      if result == Result::XWin && player == Player::X
+        || result == Result::OWin && player == Player::O
       {
         tree[child].wins += 1.0;
       }
     }
 
-    if tree[child].parent == usize::MAX {
+    if parent == usize::MAX {
       return;
     }
 
-    child = tree[child].parent;
+    child = parent;
   }
 }
 
-pub(crate) fn mcts(game: &Game) -> Cell {
+pub(crate) fn search<R: Rules>(rules: &R, state: &R::State) -> R::Move {
   macro_rules! INITIAL_TREE_CAPACITY {
     () => {
       262144
@@ -145,41 +192,72 @@ pub(crate) fn mcts(game: &Game) -> Cell {
 
   let mut tree = Vec::with_capacity(INITIAL_TREE_CAPACITY!());
   tree.push(Node {
-    childrens: Vec::with_capacity(grid::NUMBER_CELLS!()),
+    childrens: Vec::new(),
     parent: usize::MAX,
     wins: 0.0,
     playouts: 0,
-    grid: game.grid,
-    turn: game.turn,
+    state: state.clone(),
+    last_move: None,
+    odds: 1.0,
   });
 
   for _ in 0..PLAYOUTS!() {
     let leaf = select(&tree);
-    let child = expand(&mut tree, leaf);
-    let result = simulate(tree[child].grid, tree[child].turn);
-    backpropagate(&mut tree, child, result);
+    let child = expand(rules, &mut tree, leaf);
+    let result = simulate(rules, tree[child].state.clone());
+    backpropagate(rules, &mut tree, child, result);
   }
 
-  let mut best_grid = tree[tree[ROOT_NODE].childrens[0]].grid;
-  let mut most_playouts = 0;
+  let mut best_child = tree[ROOT_NODE].childrens[0];
 
   for child in &tree[ROOT_NODE].childrens {
-    if most_playouts < tree[*child].playouts {
-      most_playouts = tree[*child].playouts;
-      best_grid = tree[*child].grid;
+    if tree[best_child].playouts < tree[*child].playouts {
+      best_child = *child;
     }
   }
 
-  let player = game::get_player_from_turn(game.turn);
-  let empty_cells = grid::get_empty_cells(game.grid);
-  let mut best_cell = empty_cells[0];
+  tree[best_child].last_move.unwrap()
+}
 
-  for cell in empty_cells {
-    if grid::mark(game.grid, cell, player) == best_grid {
-      best_cell = cell;
-      break;
-    }
+pub(crate) struct Mcts;
+
+impl<R: Rules> player::Player<R> for Mcts {
+  fn get_name(&self) -> &'static str {
+    "mcts"
   }
 
-  best_cell
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    search(&game.rules, &game.state)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    game::Player,
+    grid::{self, Board},
+    random_turn::RandomTurn,
+  };
+
+  #[test]
+  fn win_is_found_through_the_coin_flips() {
+    // x . x
+    // o . o
+    // . . .
+    let grid = [
+      (0, Player::X),
+      (2, Player::X),
+      (3, Player::O),
+      (5, Player::O),
+    ]
+    .iter()
+    .fold(grid::EMPTY, |grid, (cell, player)| {
+      grid::mark(grid, *cell, *player)
+    });
+    let rules = RandomTurn::new(Board::classic());
+
+    assert_eq!(search(&rules, &(grid, 4, Some(Player::X))), 1);
+    assert_eq!(search(&rules, &(grid, 4, Some(Player::O))), 4);
+  }
 }
@@ -12,76 +12,172 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+
 use crate::{
   ai::{self, Depth, Score},
-  game::{self, Game, Player, Result, Turn},
-  grid::{self, Cell, Grid},
+  game::{Game, Player, Result, Rules},
+  player,
 };
 
-fn minimax(node: Grid, mut turn: Turn, mut depth: Depth) -> Score {
-  match grid::get_result(node, turn) {
+// a search cut off by alpha or beta only finds a bound of the score
+#[derive(Clone, Copy)]
+enum Bound {
+  Exact,
+  Lower,
+  Upper,
+}
+
+// the depth of a node follows from the number of moves played, so within one
+// search a position always has the same score and can be looked up by its key
+type Table = HashMap<u64, (Score, Bound)>;
+
+fn alphabeta<R: Rules>(
+  rules: &R,
+  table: &mut Table,
+  node: &R::State,
+  depth: Depth,
+  mut alpha: Score,
+  mut beta: Score,
+) -> Score {
+  let key = rules.get_key(node);
+
+  if let Some((score, bound)) = key.and_then(|key| table.get(&key).copied()) {
+    match bound {
+      Bound::Exact => return score,
+      Bound::Lower => alpha = std::cmp::max(alpha, score),
+      Bound::Upper => beta = std::cmp::min(beta, score),
+    }
+
+    if alpha >= beta {
+      return score;
+    }
+  }
+
+  let (low, high) = (alpha, beta);
+  let score = match rules.get_result(node) {
     Result::Draw => ai::DRAW,
     Result::XWin => ai::X_WIN - depth,
     Result::OWin => ai::O_WIN + depth,
+    Result::Continue if depth == rules.get_search_depth() => rules.evaluate(node),
+    Result::Continue if rules.get_chances(node).is_some() => {
+      panic!("minimax does not play games of chance")
+    }
     Result::Continue => {
-      let player = game::get_player_from_turn(turn);
-      turn += 1;
-      depth += 1;
-      let mut value;
-
-      if player == Player::X {
-        value = ai::O_WIN;
+      if rules.get_player(node) == Player::X {
+        let mut value = ai::O_WIN;
 
-        for cell in grid::get_empty_cells(node) {
+        for m in rules.get_candidate_moves(node) {
           value = std::cmp::max(
             value,
-            minimax(grid::mark(node, cell, Player::X), turn, depth),
+            alphabeta(rules, table, &rules.apply(node, m), depth + 1, alpha, beta),
           );
+          alpha = std::cmp::max(alpha, value);
+
+          if alpha >= beta {
+            break;
+          }
         }
+
+        value
       } else {
-        value = ai::X_WIN;
+        let mut value = ai::X_WIN;
 
-        for cell in grid::get_empty_cells(node) {
+        for m in rules.get_candidate_moves(node) {
           value = std::cmp::min(
             value,
-            minimax(grid::mark(node, cell, Player::O), turn, depth),
+            alphabeta(rules, table, &rules.apply(node, m), depth + 1, alpha, beta),
           );
+          beta = std::cmp::min(beta, value);
+
+          if alpha >= beta {
+            break;
+          }
         }
-      }
 
-      value
+        value
+      }
     }
+  };
+
+  if let Some(key) = key {
+    let bound = if score <= low {
+      Bound::Upper
+    } else if score >= high {
+      Bound::Lower
+    } else {
+      Bound::Exact
+    };
+    table.insert(key, (score, bound));
   }
-}
 
-pub(crate) fn find_best_move(game: &Game) -> Cell {
-  let empty_cells = grid::get_empty_cells(game.grid);
-  let turn = game.turn + 1;
-  let mut best_move = empty_cells[0];
-
-  if game::get_player_from_turn(game.turn) == Player::X {
-    let mut best_score = ai::O_WIN;
+  score
+}
 
-    for cell in empty_cells {
-      let score = minimax(grid::mark(game.grid, cell, Player::X), turn, 0);
+// depth limited search, unfinished games are scored by the evaluation of the
+// rules, games whose moves all fit the depth are solved
+pub(crate) fn search<R: Rules>(rules: &R, state: &R::State) -> R::Move {
+  let mut table = Table::new();
+  let mut moves = rules.get_candidate_moves(state);
+  let mut best_move = moves.next().unwrap();
+  let first = alphabeta(
+    rules,
+    &mut table,
+    &rules.apply(state, best_move),
+    1,
+    ai::O_WIN,
+    ai::X_WIN,
+  );
+
+  if rules.get_player(state) == Player::X {
+    let mut best_score = first;
+
+    for m in moves {
+      let score = alphabeta(
+        rules,
+        &mut table,
+        &rules.apply(state, m),
+        1,
+        best_score,
+        ai::X_WIN,
+      );
 
       if score > best_score {
         best_score = score;
-        best_move = cell;
+        best_move = m;
       }
     }
   } else {
-    let mut best_score = ai::X_WIN;
-
-    for cell in empty_cells {
-      let score = minimax(grid::mark(game.grid, cell, Player::O), turn, 0);
+    let mut best_score = first;
+
+    for m in moves {
+      let score = alphabeta(
+        rules,
+        &mut table,
+        &rules.apply(state, m),
+        1,
+        ai::O_WIN,
+        best_score,
+      );
 
       if score < best_score {
         best_score = score;
-        best_move = cell;
+        best_move = m;
       }
     }
   }
 
   best_move
 }
+
+pub(crate) struct Minimax;
+
+impl<R: Rules> player::Player<R> for Minimax {
+  fn get_name(&self) -> &'static str {
+    "minimax"
+  }
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    search(&game.rules, &game.state)
+  }
+}
@@ -13,10 +13,22 @@
 // limitations under the License.
 
 use crate::{
-  game::Game,
-  grid::{self, Cell},
+  game::{Game, Rules},
+  player,
 };
 
-pub(crate) fn find_best_move(game: &Game) -> Cell {
-  grid::get_random_empty_cell(game.grid)
+pub(crate) fn search<R: Rules>(rules: &R, state: &R::State) -> R::Move {
+  rules.get_random_move(state)
+}
+
+pub(crate) struct Random;
+
+impl<R: Rules> player::Player<R> for Random {
+  fn get_name(&self) -> &'static str {
+    "random"
+  }
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    search(&game.rules, &game.state)
+  }
 }
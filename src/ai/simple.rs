@@ -13,34 +13,105 @@
 // limitations under the License.
 
 use crate::{
-  game::{self, Game, Player, Result},
-  grid::{self, Cell},
+  ai::{self, Score},
+  game::{Game, Player, Result, Rules},
+  player,
 };
 
-pub(crate) fn find_best_move(game: &Game) -> Cell {
-  let ai = game::get_player_from_turn(game.turn);
-  let opponent = if ai == Player::X {
+fn is_win(result: &Result, player: Player) -> bool {
+  match result {
+    Result::XWin => player == Player::X,
+    Result::OWin => player == Player::O,
+    _ => false,
+  }
+}
+
+// whether the player may win with their next move, whichever way the coins fall
+fn can_win<R: Rules>(rules: &R, state: &R::State, player: Player) -> bool {
+  if rules.get_result(state) != Result::Continue {
+    return false;
+  }
+
+  if let Some(chances) = rules.get_chances(state) {
+    return chances
+      .iter()
+      .any(|(state, _)| can_win(rules, state, player));
+  }
+
+  rules.get_player(state) == player
+    && rules
+      .get_candidate_moves(state)
+      .any(|m| is_win(&rules.get_result(&rules.apply(state, m)), player))
+}
+
+// whether the player wins with their next move whatever the opponent does,
+// coin flips are left out
+fn is_forced<R: Rules>(rules: &R, state: &R::State, player: Player) -> bool {
+  match rules.get_result(state) {
+    Result::Continue if rules.get_chances(state).is_some() => false,
+    Result::Continue if rules.get_player(state) == player => rules
+      .get_candidate_moves(state)
+      .any(|m| is_win(&rules.get_result(&rules.apply(state, m)), player)),
+    Result::Continue => rules
+      .get_candidate_moves(state)
+      .all(|m| is_forced(rules, &rules.apply(state, m), player)),
+    result => is_win(&result, player),
+  }
+}
+
+// one move ahead, a winning move first, then the moves the opponent cannot win
+// after, those winning next move whatever the reply first, ranked by the
+// evaluation of the rules
+pub(crate) fn search<R: Rules>(rules: &R, state: &R::State) -> R::Move {
+  let player = rules.get_player(state);
+  let opponent = if player == Player::X {
     Player::O
   } else {
     Player::X
   };
-  let turn = game.turn + 1;
-  let empty_cells = grid::get_empty_cells(game.grid);
-  let mut best_move = empty_cells[0];
+  let mut best_move = None;
+  let mut best_rank = (0, Score::MIN);
 
-  for cell in empty_cells {
-    let mut result = grid::get_result(grid::mark(game.grid, cell, ai), turn);
+  for m in rules.get_candidate_moves(state) {
+    let next = rules.apply(state, m);
+    let result = rules.get_result(&next);
+    let tier = if is_win(&result, player) {
+      3
+    } else if is_win(&result, opponent) || can_win(rules, &next, opponent) {
+      0
+    } else if is_forced(rules, &next, player) {
+      2
+    } else {
+      1
+    };
+    let mut score = match result {
+      Result::Continue => rules.evaluate(&next),
+      Result::Draw => ai::DRAW,
+      Result::XWin => ai::X_WIN,
+      Result::OWin => ai::O_WIN,
+    };
 
-    if result != Result::Continue && result != Result::Draw {
-      return cell;
+    if player == Player::O {
+      score = ai::X_WIN - score;
     }
 
-    result = grid::get_result(grid::mark(game.grid, cell, opponent), turn);
-
-    if result != Result::Continue && result != Result::Draw {
-      best_move = cell;
+    if best_move.is_none() || (tier, score) > best_rank {
+      best_move = Some(m);
+      best_rank = (tier, score);
     }
   }
 
-  best_move
+  best_move.unwrap()
+}
+
+pub(crate) struct Simple;
+
+impl<R: Rules> player::Player<R> for Simple {
+  fn get_name(&self) -> &'static str {
+    "simple"
+  }
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    search(&game.rules, &game.state)
+  }
 }
@@ -12,12 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::grid::{self, Cell, Grid};
+use crate::{
+  ai::{self, Depth, Score},
+  grid::{self, Board, Cell, Grid},
+  player, tactics,
+};
 pub(crate) type Turn = i8;
-pub(crate) const TURN_MAX: Turn = 9;
-const TURN_MIN: Turn = 0;
+// largest board searched to the end, and the depth on larger ones
+const SOLVED_CELLS: usize = 16;
+const BOARD_DEPTH: Depth = 4;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub(crate) enum Player {
   X,
   O,
@@ -31,9 +36,473 @@ pub(crate) enum Result {
   OWin,
 }
 
-pub(crate) struct Game {
-  pub(crate) grid: Grid,
-  pub(crate) turn: Turn,
+pub(crate) struct Outcome {
+  pub(crate) result: Result,
+  pub(crate) lines: Vec<Vec<Cell>>,
+}
+
+// a game being played, with the moves played so far and the states they were
+// played from, so moves can be taken back and played again
+pub(crate) struct Game<R: Rules> {
+  pub(crate) rules: R,
+  pub(crate) start: R::State,
+  pub(crate) state: R::State,
+  pub(crate) moves: Vec<R::Move>,
+  states: Vec<R::State>,
+  // moves taken back with the states they were played from, which hold the
+  // outcome of any chance, the last one first to be played again
+  undone: Vec<(R::State, R::Move)>,
+}
+
+impl<R: Rules> Game<R> {
+  pub(crate) fn new(rules: R, state: R::State) -> Game<R> {
+    Game {
+      rules,
+      start: state.clone(),
+      state,
+      moves: Vec::new(),
+      states: Vec::new(),
+      undone: Vec::new(),
+    }
+  }
+
+  pub(crate) fn get_result(&self) -> Result {
+    self.rules.get_result(&self.state)
+  }
+
+  pub(crate) fn get_player(&self) -> Player {
+    self.rules.get_player(&self.state)
+  }
+
+  // a new move drops the moves taken back
+  pub(crate) fn play(&mut self, m: R::Move) {
+    self.undone.clear();
+    self.push(m);
+  }
+
+  fn push(&mut self, m: R::Move) {
+    let next = self.rules.apply(&self.state, m);
+    self.states.push(std::mem::replace(&mut self.state, next));
+    self.moves.push(m);
+  }
+
+  pub(crate) fn undo(&mut self) -> Option<R::Move> {
+    let m = self.moves.pop()?;
+    self.state = self.states.pop().unwrap();
+    self.undone.push((self.state.clone(), m));
+    Some(m)
+  }
+
+  pub(crate) fn redo(&mut self) -> Option<R::Move> {
+    let (state, m) = self.undone.pop()?;
+    self.state = state;
+    self.push(m);
+    Some(m)
+  }
+
+  // moves played so far with the states they were played from
+  pub(crate) fn get_history(&self) -> impl Iterator<Item = (&R::State, R::Move)> {
+    self.states.iter().zip(self.moves.iter().copied())
+  }
+
+  // back to the state the game started from, with no moves to play again
+  pub(crate) fn restart(&mut self) {
+    self.state = self.start.clone();
+    self.moves.clear();
+    self.states.clear();
+    self.undone.clear();
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ParseError {
+  MissingSide,
+  TrailingInput(String),
+  InvalidMark(char),
+  RowLength {
+    row: usize,
+    expected: usize,
+    found: usize,
+  },
+  InvalidBoard,
+  InvalidK(String),
+  InvalidSide(String),
+  WrongSide(char),
+  Illegal(grid::PositionError),
+}
+
+impl std::fmt::Display for ParseError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      ParseError::MissingSide => write!(f, "missing side to move"),
+      ParseError::TrailingInput(input) => write!(f, "unexpected input '{}'", input),
+      ParseError::InvalidMark(mark) => {
+        write!(f, "invalid mark '{}', expected 'x', 'o' or '.'", mark)
+      }
+      ParseError::RowLength {
+        row,
+        expected,
+        found,
+      } => write!(f, "row {} has {} cells, expected {}", row, found, expected),
+      ParseError::InvalidBoard => write!(f, "unsupported board size"),
+      ParseError::InvalidK(k) => write!(f, "invalid number of marks in a row '{}'", k),
+      ParseError::InvalidSide(side) => {
+        write!(f, "invalid side to move '{}', expected 'x' or 'o'", side)
+      }
+      ParseError::WrongSide(side) => {
+        write!(f, "'{}' cannot be to move with this number of marks", side)
+      }
+      ParseError::Illegal(error) => write!(f, "illegal position, {}", error),
+    }
+  }
+}
+
+// rows from top to bottom separated by '|', then the side to move and, when it
+// differs from the shorter side of the board, the number of marks in a row,
+// e.g. "xo.|.x.|..o x"
+impl std::str::FromStr for Game<Board> {
+  type Err = ParseError;
+
+  fn from_str(text: &str) -> std::result::Result<Game<Board>, ParseError> {
+    let mut fields = text.split_whitespace();
+    let rows: Vec<&str> = fields.next().unwrap_or("").split('|').collect();
+    let side = fields.next().ok_or(ParseError::MissingSide)?;
+    let k = fields.next();
+
+    if let Some(field) = fields.next() {
+      return Err(ParseError::TrailingInput(field.to_string()));
+    }
+
+    let width = rows[0].chars().count();
+    let height = rows.len();
+
+    for (row, marks) in rows.iter().enumerate() {
+      if marks.chars().count() != width {
+        return Err(ParseError::RowLength {
+          row: row + 1,
+          expected: width,
+          found: marks.chars().count(),
+        });
+      }
+    }
+
+    if width > u8::MAX as usize || height > u8::MAX as usize {
+      return Err(ParseError::InvalidBoard);
+    }
+
+    let (width, height) = (width as u8, height as u8);
+    let k = match k {
+      Some(k) => k
+        .parse::<u8>()
+        .map_err(|_| ParseError::InvalidK(k.to_string()))?,
+      None => std::cmp::min(width, height),
+    };
+
+    if !Board::is_valid(width, height, k) {
+      return Err(
+        if width > 0 && height > 0 && k > std::cmp::max(width, height) {
+          ParseError::InvalidK(k.to_string())
+        } else {
+          ParseError::InvalidBoard
+        },
+      );
+    }
+
+    let mut grid = grid::EMPTY;
+
+    for (cell, mark) in rows.iter().flat_map(|row| row.chars()).enumerate() {
+      match mark.to_ascii_lowercase() {
+        'x' => grid = grid::mark(grid, cell as Cell, Player::X),
+        'o' => grid = grid::mark(grid, cell as Cell, Player::O),
+        '.' => {}
+        _ => return Err(ParseError::InvalidMark(mark)),
+      }
+    }
+
+    let player = match side {
+      "x" | "X" => Player::X,
+      "o" | "O" => Player::O,
+      _ => return Err(ParseError::InvalidSide(side.to_string())),
+    };
+    let board = Board::new(width, height, k);
+    let turn = grid::get_turn(grid);
+    grid::validate(&board, grid, turn).map_err(ParseError::Illegal)?;
+
+    if get_player_from_turn(turn) != player {
+      return Err(ParseError::WrongSide(side.chars().next().unwrap()));
+    }
+
+    Ok(Game::new(board, (grid, turn)))
+  }
+}
+
+impl std::fmt::Display for Game<Board> {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let (board, (grid, turn)) = (&self.rules, self.state);
+
+    for row in 0..board.height {
+      if row > 0 {
+        write!(f, "|")?;
+      }
+
+      for column in 0..board.width {
+        let cell = row * board.width + column;
+        write!(
+          f,
+          "{}",
+          if grid.x & 1 << cell != 0 {
+            'x'
+          } else if grid.o & 1 << cell != 0 {
+            'o'
+          } else {
+            '.'
+          }
+        )?;
+      }
+    }
+
+    write!(
+      f,
+      " {}",
+      match get_player_from_turn(turn) {
+        Player::X => 'x',
+        Player::O => 'o',
+      }
+    )?;
+
+    if board.k != std::cmp::min(board.width, board.height) {
+      write!(f, " {}", board.k)?;
+    }
+
+    Ok(())
+  }
+}
+
+// a two player game with perfect information, the rules are fixed for a game
+// and the state changes with every move
+pub(crate) trait Rules {
+  type State: Clone;
+  type Move: Copy + PartialEq;
+  type Moves<'a>: Iterator<Item = Self::Move>
+  where
+    Self: 'a;
+
+  fn get_moves<'a>(&'a self, state: &Self::State) -> Self::Moves<'a>;
+
+  fn apply(&self, state: &Self::State, m: Self::Move) -> Self::State;
+
+  fn get_result(&self, state: &Self::State) -> Result;
+
+  fn get_player(&self, state: &Self::State) -> Player;
+
+  // states a chance node leads to with their odds, none when a player moves
+  fn get_chances(&self, _state: &Self::State) -> Option<Vec<(Self::State, f32)>> {
+    None
+  }
+
+  // moves worth searching, games with too many moves to look at all of them
+  // leave out the hopeless ones
+  fn get_candidate_moves<'a>(&'a self, state: &Self::State) -> Self::Moves<'a> {
+    self.get_moves(state)
+  }
+
+  fn get_random_move(&self, state: &Self::State) -> Self::Move {
+    use rand::prelude::IteratorRandom;
+
+    self
+      .get_moves(state)
+      .choose(&mut rand::thread_rng())
+      .unwrap()
+  }
+
+  // plies searched by minimax before the evaluation takes over
+  fn get_search_depth(&self) -> Depth {
+    6
+  }
+
+  // guess of the score of an unfinished game, between ai::O_WIN and ai::X_WIN
+  fn evaluate(&self, _state: &Self::State) -> Score {
+    ai::DRAW
+  }
+
+  // side played by a player, its symbol unless the game gives roles
+  fn get_side_name(&self, player: Player) -> &'static str {
+    match player {
+      Player::X => "x",
+      Player::O => "o",
+    }
+  }
+
+  // key of the state in the transposition tables of the searches, states with
+  // the same key must have the same score and the same number of moves played,
+  // none when the states are not worth storing
+  fn get_key(&self, _state: &Self::State) -> Option<u64> {
+    None
+  }
+
+  fn print(&self, state: &Self::State);
+
+  // result of a finished game
+  fn print_outcome(&self, state: &Self::State) {
+    match self.get_result(state) {
+      Result::XWin => println!("result: {} win", self.get_side_name(Player::X)),
+      Result::OWin => println!("result: {} win", self.get_side_name(Player::O)),
+      _ => println!("result: draw"),
+    }
+  }
+
+  // text parse_move reads as the move
+  fn get_move_name(&self, state: &Self::State, m: Self::Move) -> String;
+
+  fn parse_move(&self, state: &Self::State, input: &str) -> Option<Self::Move>;
+
+  // why parse_move found no move in the input
+  fn get_move_error(&self, _state: &Self::State, _input: &str) -> &'static str {
+    "invalid move"
+  }
+}
+
+impl Rules for Board {
+  type State = (Grid, Turn);
+  type Move = Cell;
+  type Moves<'a> = grid::MoveSet<'a>;
+
+  fn get_moves<'a>(&'a self, state: &(Grid, Turn)) -> grid::MoveSet<'a> {
+    grid::get_empty_cells(self, state.0)
+  }
+
+  fn apply(&self, state: &(Grid, Turn), cell: Cell) -> (Grid, Turn) {
+    (
+      grid::mark(state.0, cell, get_player_from_turn(state.1)),
+      state.1 + 1,
+    )
+  }
+
+  fn get_result(&self, state: &(Grid, Turn)) -> Result {
+    grid::get_result(self, state.0, state.1)
+  }
+
+  fn get_player(&self, state: &(Grid, Turn)) -> Player {
+    get_player_from_turn(state.1)
+  }
+
+  fn get_random_move(&self, state: &(Grid, Turn)) -> Cell {
+    grid::get_random_empty_cell(self, state.0)
+  }
+
+  // moves mapped onto each other by a symmetry of the grid have the same
+  // score, so one of each kind is picked on the canonical grid, where
+  // symmetric grids pick the same ones, and mapped back
+  fn get_candidate_moves<'a>(&'a self, state: &(Grid, Turn)) -> grid::MoveSet<'a> {
+    // only the identity leaves most grids past the opening as they are
+    if grid::get_symmetries(self, state.0).count_ones() == 1 {
+      return self.get_moves(state);
+    }
+
+    let (canonical, transform) = grid::canonicalize(self, state.0);
+    let symmetries = grid::get_symmetries(self, canonical);
+    let mut picked: grid::Bitboard = 0;
+    let mut bits = 0;
+
+    for cell in grid::get_empty_cells(self, canonical) {
+      if grid::get_symmetric_cells(self, cell, symmetries) & picked == 0 {
+        picked |= 1 << cell;
+        bits |= 1 << grid::inverse_transform_cell(self, cell, transform);
+      }
+    }
+
+    grid::get_move_set(self, bits)
+  }
+
+  // games on small boards end before the search stops, so they are solved,
+  // the evaluation takes over on larger ones and on a torus, whose lines
+  // wrapping around leave too many positions
+  fn get_search_depth(&self) -> Depth {
+    if self.number_cells() <= SOLVED_CELLS && !self.toroidal {
+      self.number_cells() as Depth
+    } else {
+      BOARD_DEPTH
+    }
+  }
+
+  fn evaluate(&self, state: &(Grid, Turn)) -> Score {
+    let x = tactics::analyze(self, state.0, Player::X);
+    let o = tactics::analyze(self, state.0, Player::O);
+    let mut score = 4 * (x.wins.count_ones() as Score - o.wins.count_ones() as Score)
+      + 2 * (x.open_twos as Score - o.open_twos as Score)
+      + x.open_ones as Score
+      - o.open_ones as Score;
+
+    if self.misere {
+      score = -score;
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  // symmetric grids have the same score
+  fn get_key(&self, state: &(Grid, Turn)) -> Option<u64> {
+    Some(grid::get_key(self, grid::canonicalize(self, state.0).0))
+  }
+
+  fn print(&self, state: &(Grid, Turn)) {
+    grid::print(self, state.0);
+  }
+
+  // the completed lines and the position reached
+  fn print_outcome(&self, state: &(Grid, Turn)) {
+    let outcome = grid::get_outcome(self, state.0, state.1);
+    let lines: Vec<String> = outcome
+      .lines
+      .iter()
+      .map(|line| {
+        line
+          .iter()
+          .map(|cell| grid::get_cell_name(self, *cell))
+          .collect::<Vec<String>>()
+          .join(" ")
+      })
+      .collect();
+    println!(
+      "result: {}",
+      match outcome.result {
+        Result::XWin => format!("x win ({})", lines.join(", ")),
+        Result::OWin => format!("o win ({})", lines.join(", ")),
+        _ => String::from("draw"),
+      }
+    );
+    println!("position: {}", Game::new(self.clone(), *state));
+  }
+
+  fn get_move_name(&self, _state: &(Grid, Turn), cell: Cell) -> String {
+    if self.gravity {
+      (cell % self.width + 1).to_string()
+    } else {
+      grid::get_cell_name(self, cell)
+    }
+  }
+
+  fn parse_move(&self, state: &(Grid, Turn), input: &str) -> Option<Cell> {
+    if self.gravity {
+      return grid::get_column_from_name(self, input)
+        .and_then(|column| grid::get_drop_cell(self, state.0, column));
+    }
+
+    grid::get_cell_from_name(self, input).filter(|cell| grid::is_cell_empty(state.0, *cell))
+  }
+
+  fn get_move_error(&self, _state: &(Grid, Turn), input: &str) -> &'static str {
+    if self.gravity {
+      match grid::get_column_from_name(self, input) {
+        Some(_) => "this column is full",
+        None => "invalid input",
+      }
+    } else if grid::get_cell_from_name(self, input).is_some() {
+      "this cell is not empty"
+    } else {
+      "invalid input"
+    }
+  }
 }
 
 pub(crate) fn get_player_from_turn(turn: Turn) -> Player {
@@ -44,74 +513,124 @@ pub(crate) fn get_player_from_turn(turn: Turn) -> Player {
   }
 }
 
-pub(crate) fn play(player_x: fn(&Game) -> Cell, player_o: fn(&Game) -> Cell) {
-  let mut game = Game {
-    grid: grid::EMPTY,
-    turn: TURN_MIN,
-  };
-  let mut result;
-  grid::print(game.grid);
+pub(crate) fn get_random_chance<S: Clone>(chances: &[(S, f32)]) -> S {
+  let mut odds = rand::random::<f32>();
+
+  for (state, chance) in chances {
+    if odds < *chance {
+      return state.clone();
+    }
+
+    odds -= chance;
+  }
 
-  loop {
-    game.grid = grid::mark(game.grid, player_x(&game), Player::X);
-    game.turn += 1;
-    grid::print(game.grid);
-    result = grid::get_result(game.grid, game.turn);
+  chances.last().unwrap().0.clone()
+}
 
-    if result != Result::Continue {
-      break;
+// the game is saved after every move, so an unfinished game is not lost
+pub(crate) fn play<'a, R: Rules>(
+  game: &mut Game<R>,
+  player_x: &'a mut dyn player::Player<R>,
+  player_o: &'a mut dyn player::Player<R>,
+  save: &dyn Fn(&Game<R>),
+) {
+  let mut result = game.get_result();
+  player_x.new_game(game);
+  player_o.new_game(game);
+  game.rules.print(&game.state);
+
+  while result == Result::Continue {
+    if let Some(chances) = game.rules.get_chances(&game.state) {
+      game.state = get_random_chance(&chances);
+      println!("{} to move", game.rules.get_side_name(game.get_player()));
+      continue;
     }
 
-    game.grid = grid::mark(game.grid, player_o(&game), Player::O);
-    game.turn += 1;
-    grid::print(game.grid);
-    result = grid::get_result(game.grid, game.turn);
+    let player = game.get_player();
+    let (current, opponent) = match player {
+      Player::X => (&mut *player_x, &mut *player_o),
+      Player::O => (&mut *player_o, &mut *player_x),
+    };
 
-    if result != Result::Continue {
-      break;
+    match current.choose_action(game) {
+      player::Action::Move(m) => {
+        game.play(m);
+        opponent.opponent_moved(game, m);
+      }
+      // back to the previous turn of the player, taking back the moves of the
+      // opponent since
+      player::Action::Undo => {
+        if game.undo().is_none() {
+          eprintln!("error: no move to undo");
+          continue;
+        }
+
+        while game.get_player() != player && game.undo().is_some() {}
+
+        current.history_changed(game);
+        opponent.history_changed(game);
+      }
+      player::Action::Redo => {
+        if game.redo().is_none() {
+          eprintln!("error: no move to redo");
+          continue;
+        }
+
+        while game.get_result() == Result::Continue
+          && game.get_player() != player
+          && game.redo().is_some()
+        {}
+
+        current.history_changed(game);
+        opponent.history_changed(game);
+      }
     }
+
+    game.rules.print(&game.state);
+    save(game);
+    result = game.get_result();
   }
 
-  println!(
-    "result: {}",
-    match result {
-      Result::XWin => "x win",
-      Result::OWin => "o win",
-      _ => "draw",
-    }
-  );
+  player_x.game_over(game);
+  player_o.game_over(game);
+  game.rules.print_outcome(&game.state);
 }
 
-pub(crate) fn ai_vs_ai(player_x: fn(&Game) -> Cell, player_o: fn(&Game) -> Cell) {
+// every game starts from the state of the given game
+pub(crate) fn ai_vs_ai<'a, R: Rules>(
+  game: &mut Game<R>,
+  player_x: &'a mut dyn player::Player<R>,
+  player_o: &'a mut dyn player::Player<R>,
+) {
   let mut x_win = 0;
   let mut o_win = 0;
   let mut draw = 0;
 
   for _ in 0..101 {
-    let mut game = Game {
-      grid: grid::EMPTY,
-      turn: TURN_MIN,
-    };
-    let mut result;
-
-    loop {
-      game.grid = grid::mark(game.grid, player_x(&game), Player::X);
-      game.turn += 1;
-      result = grid::get_result(game.grid, game.turn);
+    game.restart();
+    let mut result = game.get_result();
+    player_x.new_game(game);
+    player_o.new_game(game);
 
-      if result != Result::Continue {
-        break;
+    while result == Result::Continue {
+      if let Some(chances) = game.rules.get_chances(&game.state) {
+        game.state = get_random_chance(&chances);
+        continue;
       }
 
-      game.grid = grid::mark(game.grid, player_o(&game), Player::O);
-      game.turn += 1;
-      result = grid::get_result(game.grid, game.turn);
-
-      if result != Result::Continue {
-        break;
-      }
+      let (current, opponent) = match game.get_player() {
+        Player::X => (&mut *player_x, &mut *player_o),
+        Player::O => (&mut *player_o, &mut *player_x),
+      };
+      let m = current.choose_move(game);
+      game.play(m);
+      opponent.opponent_moved(game, m);
+      result = game.get_result();
     }
 
+    player_x.game_over(game);
+    player_o.game_over(game);
+
     match result {
       Result::XWin => x_win += 1,
       Result::OWin => o_win += 1,
@@ -119,5 +638,155 @@ pub(crate) fn ai_vs_ai(player_x: fn(&Game) -> Cell, player_o: fn(&Game) -> Cell)
     }
   }
 
-  println!("x win: {}\no win: {}\ndraw: {}", x_win, o_win, draw);
+  println!(
+    "{} win: {}\n{} win: {}\ndraw: {}",
+    game.rules.get_side_name(Player::X),
+    x_win,
+    game.rules.get_side_name(Player::O),
+    o_win,
+    draw
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::random_turn::RandomTurn;
+
+  // plays the given actions in turn and counts the undos and redos
+  struct Script {
+    actions: Vec<player::Action<Cell>>,
+    changes: usize,
+  }
+
+  impl Script {
+    fn new(actions: Vec<player::Action<Cell>>) -> Script {
+      Script {
+        actions,
+        changes: 0,
+      }
+    }
+  }
+
+  impl player::Player<Board> for Script {
+    fn get_name(&self) -> &'static str {
+      "script"
+    }
+
+    fn choose_move(&mut self, _game: &Game<Board>) -> Cell {
+      unreachable!()
+    }
+
+    fn choose_action(&mut self, _game: &Game<Board>) -> player::Action<Cell> {
+      self.actions.remove(0)
+    }
+
+    fn history_changed(&mut self, _game: &Game<Board>) {
+      self.changes += 1;
+    }
+  }
+
+  #[test]
+  fn both_players_are_told_about_an_undo_and_a_redo() {
+    use player::Action::{Move, Redo, Undo};
+
+    let mut game = Game::new(Board::classic(), (grid::EMPTY, 0));
+    let mut x = Script::new(vec![Move(0), Undo, Redo, Move(1), Move(2)]);
+    let mut o = Script::new(vec![Move(4), Move(3)]);
+    play(&mut game, &mut x, &mut o, &|_| {});
+
+    assert!(game.get_result() == Result::XWin);
+    assert!(game.moves == [0, 4, 1, 3, 2]);
+    assert_eq!((x.changes, o.changes), (2, 2));
+  }
+
+  // flips the coin of a random turn game for the given side and plays the cell
+  fn play_random_turn(game: &mut Game<RandomTurn>, player: Player, cell: Cell) {
+    game.state = (game.state.0, game.state.1, Some(player));
+    game.play(cell);
+  }
+
+  #[test]
+  fn redo_plays_moves_again_for_the_side_the_coin_chose() {
+    let mut game = Game::new(RandomTurn::new(Board::classic()), (grid::EMPTY, 0, None));
+    play_random_turn(&mut game, Player::O, 4);
+    play_random_turn(&mut game, Player::O, 0);
+    let played = game.state;
+
+    while game.undo().is_some() {}
+    while game.redo().is_some() {}
+
+    assert!(game.state == played);
+    assert!(game.state.0 == grid::mark(grid::mark(grid::EMPTY, 4, Player::O), 0, Player::O));
+    assert!(game
+      .get_history()
+      .all(|(state, _)| state.2 == Some(Player::O)));
+  }
+
+  fn get_parse_error(text: &str) -> ParseError {
+    text.parse::<Game<Board>>().err().unwrap()
+  }
+
+  #[test]
+  fn position_is_displayed_as_it_was_parsed() {
+    for text in ["xo.|.x.|..o x", "...|.x.|... o", "x...|....|....|.... o 3"] {
+      assert_eq!(text.parse::<Game<Board>>().unwrap().to_string(), text);
+    }
+  }
+
+  #[test]
+  fn parsed_position_holds_the_marks_and_the_turn() {
+    let game = "xo.|.x.|..o x".parse::<Game<Board>>().unwrap();
+    let mut played = Game::new(Board::classic(), (grid::EMPTY, 0));
+
+    for cell in [0, 1, 4, 8] {
+      played.play(cell);
+    }
+
+    assert!(game.state == played.state);
+  }
+
+  #[test]
+  fn invalid_positions_are_reported() {
+    assert_eq!(get_parse_error("xo.|.x.|..o"), ParseError::MissingSide);
+    assert_eq!(
+      get_parse_error("xo.|.x.|..o x 3 o"),
+      ParseError::TrailingInput(String::from("o"))
+    );
+    assert_eq!(
+      get_parse_error("x-.|...|... o"),
+      ParseError::InvalidMark('-')
+    );
+    assert_eq!(
+      get_parse_error("xo.|.x|..o x"),
+      ParseError::RowLength {
+        row: 2,
+        expected: 3,
+        found: 2
+      }
+    );
+    assert_eq!(
+      get_parse_error(
+        "........|........|........|........|........|........|........|........|........ x"
+      ),
+      ParseError::InvalidBoard
+    );
+    assert_eq!(
+      get_parse_error("...|...|... x 4"),
+      ParseError::InvalidK(String::from("4"))
+    );
+    assert_eq!(
+      get_parse_error("...|...|... x k"),
+      ParseError::InvalidK(String::from("k"))
+    );
+    assert_eq!(
+      get_parse_error("...|...|... z"),
+      ParseError::InvalidSide(String::from("z"))
+    );
+    assert_eq!(get_parse_error("x..|...|... x"), ParseError::WrongSide('x'));
+    assert_eq!(
+      get_parse_error("xxx|ooo|... x"),
+      ParseError::Illegal(grid::PositionError::DoubleWinner)
+    );
+  }
 }
@@ -0,0 +1,306 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  ai::{self, Depth, Score},
+  game::{self, Player, Result, Rules, Turn},
+  grid::Cell,
+};
+
+// freestyle gomoku, five or more in a row wins on a 15x15 board, cells are
+// numbered row by row from the top left corner as in grid.rs
+const SIZE: u8 = 15;
+const CELLS: usize = SIZE as usize * SIZE as usize;
+const K: u8 = 5;
+const DEPTH: Depth = 3;
+const DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+// one bit per cell over four words, the board does not fit a single bitboard
+#[derive(Clone, Copy)]
+pub(crate) struct Stones([u64; 4]);
+
+impl Stones {
+  fn has(&self, cell: Cell) -> bool {
+    self.0[cell as usize / 64] & 1 << (cell % 64) != 0
+  }
+
+  fn add(&mut self, cell: Cell) {
+    self.0[cell as usize / 64] |= 1 << (cell % 64);
+  }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+  x: Stones,
+  o: Stones,
+  // the number of moves does not fit a Turn
+  moves: u8,
+  // player who completed five in a row
+  winner: Option<Player>,
+}
+
+pub(crate) const EMPTY: State = State {
+  x: Stones([0; 4]),
+  o: Stones([0; 4]),
+  moves: 0,
+  winner: None,
+};
+
+pub(crate) struct Gomoku {
+  // every five cells in a row, for the evaluation
+  windows: Vec<[Cell; K as usize]>,
+}
+
+impl Gomoku {
+  pub(crate) fn new() -> Gomoku {
+    let mut windows = Vec::new();
+
+    for cell in 0..CELLS as Cell {
+      for direction in DIRECTIONS {
+        let mut window = [cell; K as usize];
+
+        for i in 1..K as usize {
+          match get_neighbour(window[i - 1], direction) {
+            Some(next) => window[i] = next,
+            None => break,
+          }
+        }
+
+        if window[K as usize - 1] != cell {
+          windows.push(window);
+        }
+      }
+    }
+
+    Gomoku { windows }
+  }
+}
+
+fn get_neighbour(cell: Cell, (row_step, column_step): (i8, i8)) -> Option<Cell> {
+  let row = (cell / SIZE) as i8 + row_step;
+  let column = (cell % SIZE) as i8 + column_step;
+
+  if (0..SIZE as i8).contains(&row) && (0..SIZE as i8).contains(&column) {
+    Some(row as Cell * SIZE + column as Cell)
+  } else {
+    None
+  }
+}
+
+fn is_empty(state: &State, cell: Cell) -> bool {
+  !state.x.has(cell) && !state.o.has(cell)
+}
+
+fn get_player(state: &State) -> Player {
+  game::get_player_from_turn((state.moves % 2) as Turn)
+}
+
+// only the lines through the last stone can have been completed by it
+fn is_winning(stones: &Stones, cell: Cell) -> bool {
+  DIRECTIONS.iter().any(|(row_step, column_step)| {
+    let mut count = 1;
+
+    for direction in [(*row_step, *column_step), (-row_step, -column_step)] {
+      let mut next = get_neighbour(cell, direction);
+
+      while let Some(neighbour) = next.filter(|neighbour| stones.has(*neighbour)) {
+        count += 1;
+        next = get_neighbour(neighbour, direction);
+      }
+    }
+
+    count >= K
+  })
+}
+
+impl Rules for Gomoku {
+  type State = State;
+  type Move = Cell;
+  type Moves<'a> = std::vec::IntoIter<Cell>;
+
+  fn get_moves(&self, state: &State) -> std::vec::IntoIter<Cell> {
+    (0..CELLS as Cell)
+      .filter(|cell| is_empty(state, *cell))
+      .collect::<Vec<Cell>>()
+      .into_iter()
+  }
+
+  // empty cells next to a stone, the center on an empty board
+  fn get_candidate_moves(&self, state: &State) -> std::vec::IntoIter<Cell> {
+    if state.moves == 0 {
+      return vec![CELLS as Cell / 2].into_iter();
+    }
+
+    let neighbours = [
+      (0, 1),
+      (1, 0),
+      (1, 1),
+      (1, -1),
+      (0, -1),
+      (-1, 0),
+      (-1, -1),
+      (-1, 1),
+    ];
+
+    (0..CELLS as Cell)
+      .filter(|cell| {
+        is_empty(state, *cell)
+          && neighbours.iter().any(|direction| {
+            get_neighbour(*cell, *direction).is_some_and(|neighbour| !is_empty(state, neighbour))
+          })
+      })
+      .collect::<Vec<Cell>>()
+      .into_iter()
+  }
+
+  fn apply(&self, state: &State, cell: Cell) -> State {
+    let mut next = *state;
+    let player = get_player(state);
+    let stones = match player {
+      Player::X => &mut next.x,
+      Player::O => &mut next.o,
+    };
+
+    stones.add(cell);
+
+    if is_winning(stones, cell) {
+      next.winner = Some(player);
+    }
+
+    next.moves += 1;
+    next
+  }
+
+  fn get_random_move(&self, state: &State) -> Cell {
+    use rand::Rng;
+
+    loop {
+      let cell = rand::thread_rng().gen_range(0..CELLS as Cell);
+
+      if is_empty(state, cell) {
+        return cell;
+      }
+    }
+  }
+
+  fn get_result(&self, state: &State) -> Result {
+    match state.winner {
+      Some(Player::X) => Result::XWin,
+      Some(Player::O) => Result::OWin,
+      None if state.moves as usize == CELLS => Result::Draw,
+      None => Result::Continue,
+    }
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    get_player(state)
+  }
+
+  fn get_search_depth(&self) -> Depth {
+    DEPTH
+  }
+
+  // rows of five still open to a single player, weighted by their stones
+  fn evaluate(&self, state: &State) -> Score {
+    const WEIGHTS: [Score; K as usize] = [0, 0, 1, 4, 16];
+    let mut score = 0;
+
+    for window in &self.windows {
+      let x = window.iter().filter(|cell| state.x.has(**cell)).count();
+      let o = window.iter().filter(|cell| state.o.has(**cell)).count();
+
+      if o == 0 {
+        score += WEIGHTS[x];
+      } else if x == 0 {
+        score -= WEIGHTS[o];
+      }
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  // stones of the winning rows are printed in capitals
+  fn print(&self, state: &State) {
+    let won: Vec<Cell> = self
+      .windows
+      .iter()
+      .filter(|window| {
+        window.iter().all(|cell| state.x.has(*cell)) || window.iter().all(|cell| state.o.has(*cell))
+      })
+      .flatten()
+      .copied()
+      .collect();
+    let mut text = String::new();
+
+    for row in 0..SIZE {
+      text.push_str(&format!("\n{:>2} |", SIZE - row));
+
+      for column in 0..SIZE {
+        let cell = row * SIZE + column;
+        let mark = if state.x.has(cell) {
+          'x'
+        } else if state.o.has(cell) {
+          'o'
+        } else {
+          '.'
+        };
+
+        if column > 0 {
+          text.push(' ');
+        }
+
+        text.push(if won.contains(&cell) {
+          mark.to_ascii_uppercase()
+        } else {
+          mark
+        });
+      }
+
+      text.push('|');
+    }
+
+    text.push_str("\n    ");
+
+    for column in 0..SIZE {
+      text.push((b'a' + column) as char);
+      text.push(' ');
+    }
+
+    println!("{}", text);
+  }
+
+  fn get_move_name(&self, _state: &State, cell: Cell) -> String {
+    format!("{}{}", (b'a' + cell % SIZE) as char, SIZE - cell / SIZE)
+  }
+
+  // column letter followed by the row counted from the bottom, e.g. h8 is the
+  // center
+  fn parse_move(&self, state: &State, input: &str) -> Option<Cell> {
+    let mut characters = input.chars();
+    let column = characters.next()?.to_ascii_lowercase() as u8;
+    let row = characters.as_str().parse::<u8>().ok()?;
+
+    if !(b'a'..b'a' + SIZE).contains(&column) || row == 0 || row > SIZE {
+      return None;
+    }
+
+    let cell = (SIZE - row) * SIZE + column - b'a';
+
+    if is_empty(state, cell) {
+      Some(cell)
+    } else {
+      None
+    }
+  }
+}
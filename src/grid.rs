@@ -12,661 +12,875 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::game::{self, Player, Result, Turn};
-pub(crate) type Grid = i32;
+use crate::game::{Outcome, Player, Result, Turn};
+pub(crate) type Bitboard = u64;
+pub(crate) type Cell = u8;
+
+// cells are numbered row by row starting from the top left corner, one bit
+// per cell in each of the x and o bitboards
+// | 0 | 1 | 2 |
+// | 3 | 4 | 5 |
+// | 6 | 7 | 8 |
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Grid {
+  pub(crate) x: Bitboard,
+  pub(crate) o: Bitboard,
+}
 
-macro_rules! NUMBER_CELLS {
-  () => {
-    9
-  };
+pub(crate) const EMPTY: Grid = Grid { x: 0, o: 0 };
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Transform {
+  Identity,
+  Rotate90,
+  Rotate180,
+  Rotate270,
+  FlipHorizontal,
+  FlipVertical,
+  FlipDiagonal,
+  FlipAntiDiagonal,
 }
 
-pub(crate) use NUMBER_CELLS;
+#[derive(Clone)]
+pub(crate) struct Board {
+  pub(crate) width: u8,
+  pub(crate) height: u8,
+  pub(crate) k: u8,
+  // completing a line loses instead of wins
+  pub(crate) misere: bool,
+  // marks drop to the lowest empty cell of their column
+  pub(crate) gravity: bool,
+  // lines wrap around the edges
+  pub(crate) toroidal: bool,
+  pub(crate) lines: Vec<Bitboard>,
+  order: Vec<Cell>,
+  transforms: Vec<Transform>,
+  zobrist: Vec<(u64, u64)>,
+}
 
-#[derive(Clone, Copy)]
-pub(crate) enum Cell {
-  TopLeft,
-  TopMiddle,
-  TopRight,
-  MiddleLeft,
-  MiddleMiddle,
-  MiddleRight,
-  BottomLeft,
-  BottomMiddle,
-  BottomRight,
-}
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-pub(crate) const EMPTY: Grid = 0b000_000_000_000_000_000;
-
-// x
-// | x | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | o | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_XO_TOP_LEFT: Grid = 0b000_000_001_000_000_001;
-
-// x
-// | - | x | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | o | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_XO_TOP_MIDDLE: Grid = 0b000_000_010_000_000_010;
-
-// x
-// | - | - | x |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | o |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_XO_TOP_RIGHT: Grid = 0b000_000_100_000_000_100;
-
-// x
-// | - | - | - |
-// | x | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | o | - | - |
-// | - | - | - |
-
-const CELL_XO_MIDDLE_LEFT: Grid = 0b000_001_000_000_001_000;
-
-// x
-// | - | - | - |
-// | - | x | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | o | - |
-// | - | - | - |
-
-const CELL_XO_MIDDLE_MIDDLE: Grid = 0b000_010_000_000_010_000;
-
-// x
-// | - | - | - |
-// | - | - | x |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | o |
-// | - | - | - |
-
-const CELL_XO_MIDDLE_RIGHT: Grid = 0b000_100_000_000_100_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | x | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | o | - | - |
-
-const CELL_XO_BOTTOM_LEFT: Grid = 0b001_000_000_001_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | x | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | o | - |
-
-const CELL_XO_BOTTOM_MIDDLE: Grid = 0b010_000_000_010_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | x |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | o |
-
-const CELL_XO_BOTTOM_RIGHT: Grid = 0b100_000_000_100_000_000;
-
-// x
-// | x | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_TOP_LEFT: Grid = 0b000_000_000_000_000_001;
-
-// x
-// | - | x | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_TOP_MIDDLE: Grid = 0b000_000_000_000_000_010;
-
-// x
-// | - | - | x |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_TOP_RIGHT: Grid = 0b000_000_000_000_000_100;
-
-// x
-// | - | - | - |
-// | x | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_MIDDLE_LEFT: Grid = 0b000_000_000_000_001_000;
-
-// x
-// | - | - | - |
-// | - | x | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_MIDDLE_MIDDLE: Grid = 0b000_000_000_000_010_000;
-
-// x
-// | - | - | - |
-// | - | - | x |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_MIDDLE_RIGHT: Grid = 0b000_000_000_000_100_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | x | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_BOTTOM_LEFT: Grid = 0b000_000_000_001_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | x | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_BOTTOM_MIDDLE: Grid = 0b000_000_000_010_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | x |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_X_BOTTOM_RIGHT: Grid = 0b000_000_000_100_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | o | - | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_O_TOP_LEFT: Grid = 0b000_000_001_000_000_000;
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | o | - |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_O_TOP_MIDDLE: Grid = 0b000_000_010_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | o |
-// | - | - | - |
-// | - | - | - |
-
-const CELL_O_TOP_RIGHT: Grid = 0b000_000_100_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | o | - | - |
-// | - | - | - |
-
-const CELL_O_MIDDLE_LEFT: Grid = 0b000_001_000_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | o | - |
-// | - | - | - |
-
-const CELL_O_MIDDLE_MIDDLE: Grid = 0b000_010_000_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | o |
-// | - | - | - |
-
-const CELL_O_MIDDLE_RIGHT: Grid = 0b000_100_000_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | o | - | - |
-
-const CELL_O_BOTTOM_LEFT: Grid = 0b001_000_000_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | o | - |
-
-const CELL_O_BOTTOM_MIDDLE: Grid = 0b010_000_000_000_000_000;
-
-// x
-// | - | - | - |
-// | - | - | - |
-// | - | - | - |
-// o
-// | - | - | - |
-// | - | - | - |
-// | - | - | o |
-
-const CELL_O_BOTTOM_RIGHT: Grid = 0b100_000_000_000_000_000;
-
-pub(crate) fn get_result(grid: Grid, turn: Turn) -> Result {
-  // x
-  // | x | - | - |
-  // | - | x | - |
-  // | - | - | x |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_TOP_LEFT_TO_BOTTOM_RIGHT: Grid = 0b000_000_000_100_010_001;
-
-  // x
-  // | - | - | x |
-  // | - | x | - |
-  // | x | - | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_TOP_RIGHT_TO_BOTTOM_LEFT: Grid = 0b000_000_000_001_010_100;
-
-  // x
-  // | x | x | x |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_TOP_ROW: Grid = 0b000_000_000_000_000_111;
-
-  // x
-  // | - | - | - |
-  // | x | x | x |
-  // | - | - | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_MIDDLE_ROW: Grid = 0b000_000_000_000_111_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | x | x | x |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_BOTTOM_ROW: Grid = 0b000_000_000_111_000_000;
-
-  // x
-  // | x | - | - |
-  // | x | - | - |
-  // | x | - | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_LEFT_COLUMN: Grid = 0b000_000_000_001_001_001;
-
-  // x
-  // | - | x | - |
-  // | - | x | - |
-  // | - | x | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_MIDDLE_COLUMN: Grid = 0b000_000_000_010_010_010;
-
-  // x
-  // | - | - | x |
-  // | - | - | x |
-  // | - | - | x |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-
-  const X_WIN_RIGHT_COLUMN: Grid = 0b000_000_000_100_100_100;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | o | - | - |
-  // | - | o | - |
-  // | - | - | o |
-
-  const O_WIN_TOP_LEFT_TO_BOTTOM_RIGHT: Grid = 0b100_010_001_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | - | o |
-  // | - | o | - |
-  // | o | - | - |
-
-  const O_WIN_TOP_RIGHT_TO_BOTTOM_LEFT: Grid = 0b001_010_100_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | o | o | o |
-  // | - | - | - |
-  // | - | - | - |
-
-  const O_WIN_TOP_ROW: Grid = 0b000_000_111_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | - | - |
-  // | o | o | o |
-  // | - | - | - |
-
-  const O_WIN_MIDDLE_ROW: Grid = 0b000_111_000_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | - | - |
-  // | - | - | - |
-  // | o | o | o |
-
-  const O_WIN_BOTTOM_ROW: Grid = 0b111_000_000_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | o | - | - |
-  // | o | - | - |
-  // | o | - | - |
-
-  const O_WIN_LEFT_COLUMN: Grid = 0b001_001_001_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | o | - |
-  // | - | o | - |
-  // | - | o | - |
-
-  const O_WIN_MIDDLE_COLUMN: Grid = 0b010_010_010_000_000_000;
-
-  // x
-  // | - | - | - |
-  // | - | - | - |
-  // | - | - | - |
-  // o
-  // | - | - | o |
-  // | - | - | o |
-  // | - | - | o |
-
-  const O_WIN_RIGHT_COLUMN: Grid = 0b100_100_100_000_000_000;
-
-  if grid & X_WIN_TOP_LEFT_TO_BOTTOM_RIGHT == X_WIN_TOP_LEFT_TO_BOTTOM_RIGHT
-    || grid & X_WIN_TOP_RIGHT_TO_BOTTOM_LEFT == X_WIN_TOP_RIGHT_TO_BOTTOM_LEFT
-    || grid & X_WIN_TOP_ROW == X_WIN_TOP_ROW
-    || grid & X_WIN_MIDDLE_ROW == X_WIN_MIDDLE_ROW
-    || grid & X_WIN_BOTTOM_ROW == X_WIN_BOTTOM_ROW
-    || grid & X_WIN_LEFT_COLUMN == X_WIN_LEFT_COLUMN
-    || grid & X_WIN_MIDDLE_COLUMN == X_WIN_MIDDLE_COLUMN
-    || grid & X_WIN_RIGHT_COLUMN == X_WIN_RIGHT_COLUMN
-  {
-    Result::XWin
-  } else if grid & O_WIN_TOP_LEFT_TO_BOTTOM_RIGHT == O_WIN_TOP_LEFT_TO_BOTTOM_RIGHT
-    || grid & O_WIN_TOP_RIGHT_TO_BOTTOM_LEFT == O_WIN_TOP_RIGHT_TO_BOTTOM_LEFT
-    || grid & O_WIN_TOP_ROW == O_WIN_TOP_ROW
-    || grid & O_WIN_MIDDLE_ROW == O_WIN_MIDDLE_ROW
-    || grid & O_WIN_BOTTOM_ROW == O_WIN_BOTTOM_ROW
-    || grid & O_WIN_LEFT_COLUMN == O_WIN_LEFT_COLUMN
-    || grid & O_WIN_MIDDLE_COLUMN == O_WIN_MIDDLE_COLUMN
-    || grid & O_WIN_RIGHT_COLUMN == O_WIN_RIGHT_COLUMN
-  {
-    Result::OWin
-  } else if turn == game::TURN_MAX {
+impl Board {
+  pub(crate) fn is_valid(width: u8, height: u8, k: u8) -> bool {
+    width > 0
+      && height > 0
+      && width as u32 * height as u32 <= Bitboard::BITS
+      && k > 0
+      && k <= std::cmp::max(width, height)
+  }
+
+  pub(crate) fn new(width: u8, height: u8, k: u8) -> Board {
+    if !Board::is_valid(width, height, k) {
+      panic!("invalid board");
+    }
+
+    Board::with_lines(width, height, k, generate_lines(width, height, k, false))
+  }
+
+  // rows, columns and diagonals continue on the opposite edge
+  pub(crate) fn toroidal(width: u8, height: u8, k: u8) -> Board {
+    if !Board::is_valid(width, height, k) {
+      panic!("invalid board");
+    }
+
+    let mut board = Board::with_lines(width, height, k, generate_lines(width, height, k, true));
+    board.toroidal = true;
+    board
+  }
+
+  // board with its own set of winning lines, cells are still numbered row by
+  // row so that other shapes can be laid out in the rows
+  pub(crate) fn with_lines(width: u8, height: u8, k: u8, lines: Vec<Bitboard>) -> Board {
+    if !Board::is_valid(width, height, k) {
+      panic!("invalid board");
+    }
+
+    // cells lying on more lines are tried first, which gives the usual
+    // center, corners, edges ordering on the classic board
+    let mut order: Vec<Cell> = (0..width * height).collect();
+    order.sort_by_key(|cell| {
+      std::cmp::Reverse(lines.iter().filter(|line| *line & (1 << cell) != 0).count())
+    });
+
+    // rotating by a quarter turn or flipping along a diagonal only maps the
+    // board onto itself when it is square
+    let transforms = if width == height {
+      vec![
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::FlipDiagonal,
+        Transform::FlipAntiDiagonal,
+      ]
+    } else {
+      vec![
+        Transform::Identity,
+        Transform::Rotate180,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+      ]
+    };
+
+    // fixed seed so keys of large boards stay the same between runs
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next_key = || {
+      seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+      let mut key = seed;
+      key = (key ^ key >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+      key = (key ^ key >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+      key ^ key >> 31
+    };
+    let zobrist = (0..width * height)
+      .map(|_| (next_key(), next_key()))
+      .collect();
+
+    Board {
+      width,
+      height,
+      k,
+      misere: false,
+      gravity: false,
+      toroidal: false,
+      lines,
+      order,
+      transforms,
+      zobrist,
+    }
+  }
+
+  pub(crate) fn classic() -> Board {
+    Board::new(3, 3, 3)
+  }
+
+  pub(crate) fn number_cells(&self) -> usize {
+    self.width as usize * self.height as usize
+  }
+
+  // 3^40 is the largest power of three that fits in a u64
+  pub(crate) fn is_indexable(&self) -> bool {
+    self.number_cells() <= 40
+  }
+
+  pub(crate) fn is_classic(&self) -> bool {
+    self.width == 3 && self.height == 3 && self.k == 3
+  }
+}
+
+// every k cells in a row, column or diagonal, when wrapping around a line
+// leaving the board continues on the opposite edge and lines longer than the
+// board in their direction are left out as they would cross a cell twice
+fn generate_lines(width: u8, height: u8, k: u8, wrap: bool) -> Vec<Bitboard> {
+  let (width, height, k) = (width as i32, height as i32, k as i32);
+  let mut lines = Vec::new();
+
+  for row in 0..height {
+    for column in 0..width {
+      for (row_step, column_step) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+        let last_row = row + row_step * (k - 1);
+        let last_column = column + column_step * (k - 1);
+
+        if wrap {
+          if row_step != 0 && k > height || column_step != 0 && k > width {
+            continue;
+          }
+        } else if last_row >= height || last_column < 0 || last_column >= width {
+          continue;
+        }
+
+        let mut line = 0;
+
+        for i in 0..k {
+          let cell_row = (row + row_step * i).rem_euclid(height);
+          let cell_column = (column + column_step * i).rem_euclid(width);
+          line |= 1 << (cell_row * width + cell_column);
+        }
+
+        lines.push(line);
+      }
+    }
+  }
+
+  // wrapped lines as long as the board are found from each of their cells
+  if wrap {
+    lines.sort_unstable();
+    lines.dedup();
+  }
+
+  lines
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum PositionError {
+  OutsideBoard,
+  Overlapping(Cell),
+  WrongMoveCount { x: u32, o: u32, turn: Turn },
+  DoubleWinner,
+  PlayAfterWin(Player),
+  Floating(Cell),
+}
+
+impl std::fmt::Display for PositionError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      PositionError::OutsideBoard => write!(f, "marks outside of the board"),
+      PositionError::Overlapping(cell) => write!(f, "cell {} holds both x and o", cell),
+      PositionError::WrongMoveCount { x, o, turn } => write!(
+        f,
+        "{} x and {} o cannot be on the board at turn {}",
+        x, o, turn
+      ),
+      PositionError::DoubleWinner => write!(f, "both x and o have a line"),
+      PositionError::PlayAfterWin(player) => write!(
+        f,
+        "play continued after {} completed a line",
+        match player {
+          Player::X => 'x',
+          Player::O => 'o',
+        }
+      ),
+      PositionError::Floating(cell) => write!(f, "cell {} is above an empty cell", cell),
+    }
+  }
+}
+
+// a grid is reachable when x started, the players alternated and nobody
+// moved after the first line was completed
+pub(crate) fn validate(
+  board: &Board,
+  grid: Grid,
+  turn: Turn,
+) -> std::result::Result<(), PositionError> {
+  let x = grid.x.count_ones();
+  let o = grid.o.count_ones();
+
+  if get_empty_bits(board, EMPTY) & (grid.x | grid.o) != grid.x | grid.o {
+    return Err(PositionError::OutsideBoard);
+  }
+
+  if grid.x & grid.o != 0 {
+    return Err(PositionError::Overlapping(
+      (grid.x & grid.o).trailing_zeros() as Cell,
+    ));
+  }
+
+  if (x != o && x != o + 1) || x + o != turn as u32 {
+    return Err(PositionError::WrongMoveCount { x, o, turn });
+  }
+
+  let floating = (grid.x | grid.o) & !get_supported_bits(board, grid);
+
+  if board.gravity && floating != 0 {
+    return Err(PositionError::Floating(floating.trailing_zeros() as Cell));
+  }
+
+  let x_won = get_lines(board, grid.x).next().is_some();
+  let o_won = get_lines(board, grid.o).next().is_some();
+  // the last move of the winner is the only one all of their lines share
+  let last_moves =
+    |bitboard: Bitboard| get_lines(board, bitboard).fold(bitboard, |cells, line| cells & line);
+
+  if x_won && o_won {
+    Err(PositionError::DoubleWinner)
+  } else if x_won && (x != o + 1 || last_moves(grid.x) == 0) {
+    Err(PositionError::PlayAfterWin(Player::X))
+  } else if o_won && (x != o || last_moves(grid.o) == 0) {
+    Err(PositionError::PlayAfterWin(Player::O))
+  } else {
+    Ok(())
+  }
+}
+
+// lines completely covered by the given marks
+fn get_lines(board: &Board, bitboard: Bitboard) -> impl Iterator<Item = Bitboard> + '_ {
+  board
+    .lines
+    .iter()
+    .copied()
+    .filter(move |line| bitboard & line == *line)
+}
+
+pub(crate) fn get_result(board: &Board, grid: Grid, turn: Turn) -> Result {
+  if board.lines.iter().any(|line| grid.x & line == *line) {
+    if board.misere {
+      Result::OWin
+    } else {
+      Result::XWin
+    }
+  } else if board.lines.iter().any(|line| grid.o & line == *line) {
+    if board.misere {
+      Result::XWin
+    } else {
+      Result::OWin
+    }
+  } else if turn as usize == board.number_cells() {
     Result::Draw
   } else {
     Result::Continue
   }
 }
 
+pub(crate) fn get_outcome(board: &Board, grid: Grid, turn: Turn) -> Outcome {
+  let result = get_result(board, grid, turn);
+  let lines = get_lines(board, grid.x)
+    .chain(get_lines(board, grid.o))
+    .map(get_cells)
+    .collect();
+
+  Outcome { result, lines }
+}
+
+fn get_cells(mut bitboard: Bitboard) -> Vec<Cell> {
+  let mut cells = Vec::with_capacity(bitboard.count_ones() as usize);
+
+  while bitboard != 0 {
+    cells.push(bitboard.trailing_zeros() as Cell);
+    bitboard &= bitboard - 1;
+  }
+
+  cells
+}
+
 pub(crate) fn mark(grid: Grid, cell: Cell, player: Player) -> Grid {
   match player {
-    Player::X => match cell {
-      Cell::TopLeft => grid | CELL_X_TOP_LEFT,
-      Cell::TopMiddle => grid | CELL_X_TOP_MIDDLE,
-      Cell::TopRight => grid | CELL_X_TOP_RIGHT,
-      Cell::MiddleLeft => grid | CELL_X_MIDDLE_LEFT,
-      Cell::MiddleMiddle => grid | CELL_X_MIDDLE_MIDDLE,
-      Cell::MiddleRight => grid | CELL_X_MIDDLE_RIGHT,
-      Cell::BottomLeft => grid | CELL_X_BOTTOM_LEFT,
-      Cell::BottomMiddle => grid | CELL_X_BOTTOM_MIDDLE,
-      Cell::BottomRight => grid | CELL_X_BOTTOM_RIGHT,
+    Player::X => Grid {
+      x: grid.x | 1 << cell,
+      o: grid.o,
     },
-    Player::O => match cell {
-      Cell::TopLeft => grid | CELL_O_TOP_LEFT,
-      Cell::TopMiddle => grid | CELL_O_TOP_MIDDLE,
-      Cell::TopRight => grid | CELL_O_TOP_RIGHT,
-      Cell::MiddleLeft => grid | CELL_O_MIDDLE_LEFT,
-      Cell::MiddleMiddle => grid | CELL_O_MIDDLE_MIDDLE,
-      Cell::MiddleRight => grid | CELL_O_MIDDLE_RIGHT,
-      Cell::BottomLeft => grid | CELL_O_BOTTOM_LEFT,
-      Cell::BottomMiddle => grid | CELL_O_BOTTOM_MIDDLE,
-      Cell::BottomRight => grid | CELL_O_BOTTOM_RIGHT,
+    Player::O => Grid {
+      x: grid.x,
+      o: grid.o | 1 << cell,
     },
   }
 }
 
 pub(crate) fn is_cell_empty(grid: Grid, cell: Cell) -> bool {
-  const EMPTY_CELL: Grid = 0b000_000_000_000_000_000;
-
-  match cell {
-    Cell::TopLeft => grid & CELL_XO_TOP_LEFT == EMPTY_CELL,
-    Cell::TopMiddle => grid & CELL_XO_TOP_MIDDLE == EMPTY_CELL,
-    Cell::TopRight => grid & CELL_XO_TOP_RIGHT == EMPTY_CELL,
-    Cell::MiddleLeft => grid & CELL_XO_MIDDLE_LEFT == EMPTY_CELL,
-    Cell::MiddleMiddle => grid & CELL_XO_MIDDLE_MIDDLE == EMPTY_CELL,
-    Cell::MiddleRight => grid & CELL_XO_MIDDLE_RIGHT == EMPTY_CELL,
-    Cell::BottomLeft => grid & CELL_XO_BOTTOM_LEFT == EMPTY_CELL,
-    Cell::BottomMiddle => grid & CELL_XO_BOTTOM_MIDDLE == EMPTY_CELL,
-    Cell::BottomRight => grid & CELL_XO_BOTTOM_RIGHT == EMPTY_CELL,
-  }
-}
-
-pub(crate) fn get_empty_cells(grid: Grid) -> Vec<Cell> {
-  let mut cells = Vec::with_capacity(NUMBER_CELLS!());
-
-  for cell in [
-    Cell::MiddleMiddle,
-    Cell::TopLeft,
-    Cell::TopRight,
-    Cell::BottomLeft,
-    Cell::BottomRight,
-    Cell::TopMiddle,
-    Cell::MiddleLeft,
-    Cell::MiddleRight,
-    Cell::BottomMiddle,
-  ] {
-    if is_cell_empty(grid, cell) {
-      cells.push(cell);
+  (grid.x | grid.o) & 1 << cell == 0
+}
+
+// the set of empty cells of a grid, iterated in the move order of the board
+#[derive(Clone, Copy)]
+pub(crate) struct MoveSet<'a> {
+  bits: Bitboard,
+  order: &'a [Cell],
+}
+
+impl MoveSet<'_> {
+  pub(crate) fn first(self) -> Option<Cell> {
+    self
+      .order
+      .iter()
+      .copied()
+      .find(|cell| self.bits & 1 << cell != 0)
+  }
+
+  pub(crate) fn choose(&self) -> Option<Cell> {
+    if self.bits == 0 {
+      return None;
+    }
+
+    use rand::Rng;
+
+    let n = rand::thread_rng().gen_range(0..self.bits.count_ones());
+    Some(select_bit(self.bits, n))
+  }
+}
+
+impl Iterator for MoveSet<'_> {
+  type Item = Cell;
+
+  fn next(&mut self) -> Option<Cell> {
+    while self.bits != 0 {
+      let (cell, order) = self.order.split_first()?;
+      self.order = order;
+
+      if self.bits & 1 << cell != 0 {
+        self.bits &= !(1 << cell);
+        return Some(*cell);
+      }
     }
+
+    None
   }
 
-  cells
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = self.bits.count_ones() as usize;
+    (len, Some(len))
+  }
+}
+
+impl ExactSizeIterator for MoveSet<'_> {}
+
+// position of the n-th set bit, found by halving the searched range
+fn select_bit(mut bits: Bitboard, mut n: u32) -> Cell {
+  let mut position = 0;
+
+  for width in [32, 16, 8, 4, 2, 1] {
+    let mask: Bitboard = (1 << width) - 1;
+    let low = (bits & mask).count_ones();
+
+    if n >= low {
+      n -= low;
+      bits >>= width;
+      position += width;
+    }
+
+    bits &= mask;
+  }
+
+  position
 }
 
-pub(crate) fn get_random_empty_cell(grid: Grid) -> Cell {
-  use rand::prelude::SliceRandom;
+pub(crate) fn get_empty_bits(board: &Board, grid: Grid) -> Bitboard {
+  let full = if board.number_cells() as u32 == Bitboard::BITS {
+    Bitboard::MAX
+  } else {
+    (1 << board.number_cells()) - 1
+  };
 
-  *get_empty_cells(grid)
-    .choose(&mut rand::thread_rng())
-    .unwrap()
+  full & !(grid.x | grid.o)
 }
 
-pub(crate) fn print(grid: Grid) {
-  println!(
-    "\n|{} {} {}|\n|{} {} {}|\n|{} {} {}|",
-    match grid & CELL_XO_TOP_LEFT {
-      CELL_X_TOP_LEFT => 'x',
-      CELL_O_TOP_LEFT => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_TOP_MIDDLE {
-      CELL_X_TOP_MIDDLE => 'x',
-      CELL_O_TOP_MIDDLE => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_TOP_RIGHT {
-      CELL_X_TOP_RIGHT => 'x',
-      CELL_O_TOP_RIGHT => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_MIDDLE_LEFT {
-      CELL_X_MIDDLE_LEFT => 'x',
-      CELL_O_MIDDLE_LEFT => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_MIDDLE_MIDDLE {
-      CELL_X_MIDDLE_MIDDLE => 'x',
-      CELL_O_MIDDLE_MIDDLE => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_MIDDLE_RIGHT {
-      CELL_X_MIDDLE_RIGHT => 'x',
-      CELL_O_MIDDLE_RIGHT => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_BOTTOM_LEFT {
-      CELL_X_BOTTOM_LEFT => 'x',
-      CELL_O_BOTTOM_LEFT => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_BOTTOM_MIDDLE {
-      CELL_X_BOTTOM_MIDDLE => 'x',
-      CELL_O_BOTTOM_MIDDLE => 'o',
-      _ => ' ',
-    },
-    match grid & CELL_XO_BOTTOM_RIGHT {
-      CELL_X_BOTTOM_RIGHT => 'x',
-      CELL_O_BOTTOM_RIGHT => 'o',
-      _ => ' ',
+// cells of the bottom row and cells right above a mark
+fn get_supported_bits(board: &Board, grid: Grid) -> Bitboard {
+  let full = get_empty_bits(board, EMPTY);
+  let above = |bits: Bitboard| bits.checked_shr(board.width as u32).unwrap_or(0);
+
+  full & (above(grid.x | grid.o) | !above(full))
+}
+
+// cells a mark can be played in, with gravity only the lowest empty cell of
+// each column
+pub(crate) fn get_playable_bits(board: &Board, grid: Grid) -> Bitboard {
+  if board.gravity {
+    get_empty_bits(board, grid) & get_supported_bits(board, grid)
+  } else {
+    get_empty_bits(board, grid)
+  }
+}
+
+pub(crate) fn get_move_set(board: &Board, bits: Bitboard) -> MoveSet<'_> {
+  MoveSet {
+    bits,
+    order: &board.order,
+  }
+}
+
+pub(crate) fn get_empty_cells(board: &Board, grid: Grid) -> MoveSet<'_> {
+  get_move_set(board, get_playable_bits(board, grid))
+}
+
+pub(crate) fn get_random_empty_cell(board: &Board, grid: Grid) -> Cell {
+  get_empty_cells(board, grid).choose().unwrap()
+}
+
+pub(crate) fn get_inverse_transform(transform: Transform) -> Transform {
+  match transform {
+    Transform::Rotate90 => Transform::Rotate270,
+    Transform::Rotate270 => Transform::Rotate90,
+    _ => transform,
+  }
+}
+
+pub(crate) fn transform_cell(board: &Board, cell: Cell, transform: Transform) -> Cell {
+  let width = board.width;
+  let height = board.height;
+  let row = cell / width;
+  let column = cell % width;
+  let (row, column) = match transform {
+    Transform::Identity => (row, column),
+    Transform::Rotate90 => (column, height - 1 - row),
+    Transform::Rotate180 => (height - 1 - row, width - 1 - column),
+    Transform::Rotate270 => (width - 1 - column, row),
+    Transform::FlipHorizontal => (row, width - 1 - column),
+    Transform::FlipVertical => (height - 1 - row, column),
+    Transform::FlipDiagonal => (column, row),
+    Transform::FlipAntiDiagonal => (width - 1 - column, height - 1 - row),
+  };
+
+  row * width + column
+}
+
+pub(crate) fn inverse_transform_cell(board: &Board, cell: Cell, transform: Transform) -> Cell {
+  transform_cell(board, cell, get_inverse_transform(transform))
+}
+
+fn transform_bitboard(board: &Board, mut bitboard: Bitboard, transform: Transform) -> Bitboard {
+  let mut transformed = 0;
+
+  while bitboard != 0 {
+    let cell = bitboard.trailing_zeros() as Cell;
+    transformed |= 1 << transform_cell(board, cell, transform);
+    bitboard &= bitboard - 1;
+  }
+
+  transformed
+}
+
+pub(crate) fn transform(board: &Board, grid: Grid, transform: Transform) -> Grid {
+  Grid {
+    x: transform_bitboard(board, grid.x, transform),
+    o: transform_bitboard(board, grid.o, transform),
+  }
+}
+
+fn get_transforms(board: &Board) -> impl Iterator<Item = Transform> + '_ {
+  // only mirroring the columns keeps the marks resting on each other
+  board.transforms.iter().copied().filter(|symmetry| {
+    !board.gravity || matches!(symmetry, Transform::Identity | Transform::FlipHorizontal)
+  })
+}
+
+// the canonical grid is the smallest of all symmetric grids, together with
+// the transform that maps the given grid onto it
+pub(crate) fn canonicalize(board: &Board, grid: Grid) -> (Grid, Transform) {
+  let mut canonical = (grid, Transform::Identity);
+
+  for symmetry in get_transforms(board) {
+    let transformed = transform(board, grid, symmetry);
+
+    if (transformed.x, transformed.o) < (canonical.0.x, canonical.0.o) {
+      canonical = (transformed, symmetry);
+    }
+  }
+
+  canonical
+}
+
+// transforms that leave the grid as it is, one bit per transform in the order
+// they are declared
+pub(crate) fn get_symmetries(board: &Board, grid: Grid) -> u8 {
+  get_transforms(board)
+    .filter(|symmetry| transform(board, grid, *symmetry) == grid)
+    .fold(0, |symmetries, symmetry| symmetries | 1 << symmetry as u8)
+}
+
+// cells the given symmetries map the cell onto
+pub(crate) fn get_symmetric_cells(board: &Board, cell: Cell, symmetries: u8) -> Bitboard {
+  get_transforms(board)
+    .filter(|symmetry| symmetries & 1 << *symmetry as u8 != 0)
+    .fold(0, |cells, symmetry| {
+      cells | 1 << transform_cell(board, cell, symmetry)
+    })
+}
+
+pub(crate) fn get_turn(grid: Grid) -> Turn {
+  (grid.x | grid.o).count_ones() as Turn
+}
+
+// base 3 number with one digit per cell, 0 empty, 1 x and 2 o, the first cell
+// being the least significant digit, the turn follows from the number of marks
+pub(crate) fn get_index(board: &Board, grid: Grid) -> u64 {
+  let mut index = 0;
+
+  for cell in (0..board.number_cells() as Cell).rev() {
+    index = index * 3
+      + if grid.x & 1 << cell != 0 {
+        1
+      } else if grid.o & 1 << cell != 0 {
+        2
+      } else {
+        0
+      };
+  }
+
+  index
+}
+
+pub(crate) fn get_grid_from_index(board: &Board, mut index: u64) -> Option<Grid> {
+  if !board.is_indexable() || index >= 3u64.pow(board.number_cells() as u32) {
+    return None;
+  }
+
+  let mut grid = EMPTY;
+
+  for cell in 0..board.number_cells() as Cell {
+    match index % 3 {
+      1 => grid = mark(grid, cell, Player::X),
+      2 => grid = mark(grid, cell, Player::O),
+      _ => {}
+    }
+
+    index /= 3;
+  }
+
+  Some(grid)
+}
+
+pub(crate) fn get_zobrist_key(board: &Board, grid: Grid) -> u64 {
+  let mut key = 0;
+
+  for cell in 0..board.number_cells() as Cell {
+    if grid.x & 1 << cell != 0 {
+      key ^= board.zobrist[cell as usize].0;
+    } else if grid.o & 1 << cell != 0 {
+      key ^= board.zobrist[cell as usize].1;
+    }
+  }
+
+  key
+}
+
+// exact index where it fits, zobrist hash on larger boards
+pub(crate) fn get_key(board: &Board, grid: Grid) -> u64 {
+  if board.is_indexable() {
+    get_index(board, grid)
+  } else {
+    get_zobrist_key(board, grid)
+  }
+}
+
+// classic board: numpad layout, larger boards: column letter and row number
+// counted from the bottom, e.g. a1 is the bottom left corner
+pub(crate) fn get_cell_from_name(board: &Board, name: &str) -> Option<Cell> {
+  if board.is_classic() {
+    let number = name.parse::<u8>().ok()?;
+
+    if number > 0 && number < 10 {
+      let number = number - 1;
+      return Some((2 - number / 3) * 3 + number % 3);
+    }
+
+    return None;
+  }
+
+  let mut characters = name.chars();
+  let column = characters.next()?.to_ascii_lowercase() as u8;
+  let row = characters.as_str().parse::<u8>().ok()?;
+
+  if column < b'a' || column >= b'a' + board.width || row == 0 || row > board.height {
+    return None;
+  }
+
+  Some((board.height - row) * board.width + column - b'a')
+}
+
+pub(crate) fn get_column_from_name(board: &Board, name: &str) -> Option<u8> {
+  let number = name.parse::<u8>().ok()?;
+
+  if number > 0 && number <= board.width {
+    Some(number - 1)
+  } else {
+    None
+  }
+}
+
+pub(crate) fn get_drop_cell(board: &Board, grid: Grid, column: u8) -> Option<Cell> {
+  get_empty_cells(board, grid).find(|cell| cell % board.width == column)
+}
+
+pub(crate) fn get_cell_name(board: &Board, cell: Cell) -> String {
+  let row = cell / board.width;
+  let column = cell % board.width;
+
+  if board.is_classic() {
+    ((2 - row) * 3 + column + 1).to_string()
+  } else {
+    format!("{}{}", (b'a' + column) as char, board.height - row)
+  }
+}
+
+// marks of completed lines are printed in capitals
+pub(crate) fn print(board: &Board, grid: Grid) {
+  let won = get_lines(board, grid.x)
+    .chain(get_lines(board, grid.o))
+    .fold(0, |cells, line| cells | line);
+  let mut text = String::new();
+
+  for row in 0..board.height {
+    text.push_str("\n|");
+
+    for column in 0..board.width {
+      let cell = row * board.width + column;
+
+      if column > 0 {
+        text.push(' ');
+      }
+
+      let mark = if grid.x & 1 << cell != 0 {
+        'x'
+      } else if grid.o & 1 << cell != 0 {
+        'o'
+      } else {
+        ' '
+      };
+
+      text.push(if won & 1 << cell != 0 {
+        mark.to_ascii_uppercase()
+      } else {
+        mark
+      });
+    }
+
+    text.push('|');
+  }
+
+  if board.gravity {
+    text.push_str("\n ");
+
+    for column in 1..=board.width {
+      text.push_str(&format!("{} ", column));
+    }
+  }
+
+  println!("{}", text);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get_grid(x: &[Cell], o: &[Cell]) -> Grid {
+    let grid = x
+      .iter()
+      .fold(EMPTY, |grid, cell| mark(grid, *cell, Player::X));
+    o.iter()
+      .fold(grid, |grid, cell| mark(grid, *cell, Player::O))
+  }
+
+  fn get_error(board: &Board, grid: Grid, turn: Turn) -> PositionError {
+    validate(board, grid, turn).unwrap_err()
+  }
+
+  #[test]
+  fn reachable_positions_are_valid() {
+    let board = Board::classic();
+
+    assert_eq!(validate(&board, EMPTY, 0), Ok(()));
+    assert_eq!(validate(&board, get_grid(&[0, 4], &[8]), 3), Ok(()));
+    assert_eq!(validate(&board, get_grid(&[0, 1, 2], &[3, 4]), 5), Ok(()));
+  }
+
+  #[test]
+  fn unreachable_positions_are_reported() {
+    let board = Board::classic();
+    let mut gravity = Board::classic();
+    gravity.gravity = true;
+
+    assert_eq!(
+      get_error(&board, Grid { x: 1 << 9, o: 0 }, 1),
+      PositionError::OutsideBoard
+    );
+    assert_eq!(
+      get_error(
+        &board,
+        Grid {
+          x: 1 << 4,
+          o: 1 << 4
+        },
+        2
+      ),
+      PositionError::Overlapping(4)
+    );
+    assert_eq!(
+      get_error(&board, get_grid(&[0, 1], &[]), 2),
+      PositionError::WrongMoveCount {
+        x: 2,
+        o: 0,
+        turn: 2
+      }
+    );
+    assert_eq!(
+      get_error(&board, get_grid(&[0], &[]), 0),
+      PositionError::WrongMoveCount {
+        x: 1,
+        o: 0,
+        turn: 0
+      }
+    );
+    assert_eq!(
+      get_error(&board, get_grid(&[0, 1, 2], &[3, 4, 5]), 6),
+      PositionError::DoubleWinner
+    );
+    assert_eq!(
+      get_error(&board, get_grid(&[0, 1, 2], &[3, 4, 6]), 6),
+      PositionError::PlayAfterWin(Player::X)
+    );
+    assert_eq!(
+      get_error(&board, get_grid(&[0, 1, 7, 8], &[3, 4, 5]), 7),
+      PositionError::PlayAfterWin(Player::O)
+    );
+    assert_eq!(
+      get_error(&gravity, get_grid(&[0], &[]), 1),
+      PositionError::Floating(0)
+    );
+  }
+
+  #[test]
+  fn inverse_transform_maps_cells_back() {
+    let board = Board::classic();
+
+    for transform in board.transforms.clone() {
+      for cell in 0..board.number_cells() as Cell {
+        let transformed = transform_cell(&board, cell, transform);
+
+        assert_eq!(inverse_transform_cell(&board, transformed, transform), cell);
+      }
+    }
+  }
+
+  #[test]
+  fn canonical_cells_map_back_to_the_same_marks() {
+    let board = Board::classic();
+    let grid = mark(mark(EMPTY, 5, Player::X), 6, Player::O);
+    let (canonical, transform) = canonicalize(&board, grid);
+
+    for cell in 0..board.number_cells() as Cell {
+      let original = inverse_transform_cell(&board, cell, transform);
+
+      assert_eq!(canonical.x & 1 << cell != 0, grid.x & 1 << original != 0);
+      assert_eq!(canonical.o & 1 << cell != 0, grid.o & 1 << original != 0);
+    }
+  }
+
+  #[test]
+  fn index_maps_back_to_the_grid() {
+    let board = Board::classic();
+    let indices = 3u64.pow(board.number_cells() as u32);
+
+    for index in 0..indices {
+      let grid = get_grid_from_index(&board, index).unwrap();
+
+      assert_eq!(get_index(&board, grid), index);
     }
-  );
+
+    assert!(get_grid_from_index(&board, indices).is_none());
+  }
+
+  #[test]
+  fn lines_of_a_torus_wrap_around_the_edges() {
+    let board = Board::toroidal(3, 3, 3);
+    let line: Bitboard = 1 << 2 | 1 << 3 | 1 << 7;
+
+    assert!(board.lines.contains(&line));
+    assert_eq!(board.lines.len(), 12);
+  }
+
+  #[test]
+  fn wrapped_lines_as_long_as_the_board_are_found_once() {
+    let board = Board::toroidal(3, 3, 3);
+    let row: Bitboard = 0b111;
+
+    assert_eq!(board.lines.iter().filter(|line| **line == row).count(), 1);
+  }
+
+  #[test]
+  fn moves_come_center_first_then_corners_then_edges() {
+    let board = Board::classic();
+    let moves: Vec<Cell> = get_empty_cells(&board, EMPTY).collect();
+
+    assert_eq!(moves, [4, 0, 2, 6, 8, 1, 3, 5, 7]);
+  }
+
+  #[test]
+  fn select_bit_finds_the_nth_set_bit() {
+    let bits: Bitboard = 1 | 1 << 5 | 1 << 40 | 1 << 63;
+
+    assert_eq!(select_bit(bits, 0), 0);
+    assert_eq!(select_bit(bits, 1), 5);
+    assert_eq!(select_bit(bits, 2), 40);
+    assert_eq!(select_bit(bits, 3), 63);
+    assert_eq!(select_bit(Bitboard::MAX, 63), 63);
+  }
 }
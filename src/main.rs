@@ -14,35 +14,62 @@
 
 mod ai;
 mod game;
+mod gomoku;
 mod grid;
+mod morris;
+mod notakto;
+mod numerical;
+mod order;
+mod player;
+mod quantum;
+mod qubic;
+mod random_turn;
+mod record;
+mod tactics;
+mod ultimate;
 mod user;
+mod wild;
 use crate::{
-  ai::{mcts, minimax, random, simple},
-  game::Game,
-  grid::Cell,
+  ai::{expectimax::Expectimax, mcts::Mcts, minimax::Minimax, random::Random, simple::Simple},
+  game::{Game, Rules},
+  gomoku::Gomoku,
+  grid::Board,
+  morris::Morris,
+  notakto::Notakto,
+  numerical::Numerical,
+  order::Order,
+  player::Player,
+  quantum::Quantum,
+  qubic::Qubic,
+  random_turn::RandomTurn,
+  record::Record,
+  ultimate::Ultimate,
+  user::User,
+  wild::Wild,
 };
 type PlayerId = i8;
-type Player = (fn(&Game) -> Cell, String, PlayerId);
 const MCTS: PlayerId = 0;
 const RANDOM: PlayerId = 1;
 const MINIMAX: PlayerId = 2;
 const SIMPLE: PlayerId = 3;
 const USER: PlayerId = 4;
+const EXPECTIMAX: PlayerId = 5;
 
-fn get_player(id: PlayerId) -> Player {
+fn get_player<R: Rules>(id: PlayerId) -> Box<dyn Player<R>> {
   match id {
-    MCTS => (mcts::mcts, String::from("mcts"), MCTS),
-    RANDOM => (random::find_best_move, String::from("random"), RANDOM),
-    MINIMAX => (minimax::find_best_move, String::from("minimax"), MINIMAX),
-    SIMPLE => (simple::find_best_move, String::from("simple"), SIMPLE),
-    USER => (user::get_move, String::from("user"), USER),
+    MCTS => Box::new(Mcts),
+    RANDOM => Box::new(Random),
+    MINIMAX => Box::new(Minimax),
+    SIMPLE => Box::new(Simple),
+    USER => Box::new(User),
+    EXPECTIMAX => Box::new(Expectimax),
     _ => panic!("invalid player id"),
   }
 }
 
 fn is_ai_player(player: PlayerId) -> bool {
   match player {
-    MCTS | RANDOM | MINIMAX | SIMPLE => true,
+    MCTS | RANDOM | MINIMAX | SIMPLE | EXPECTIMAX => true,
     USER => false,
     _ => panic!("invalid player id"),
   }
@@ -52,13 +79,72 @@ fn get_player_id_from_argument(argument: Option<String>) -> PlayerId {
   argument.unwrap().parse::<PlayerId>().unwrap()
 }
 
-fn parse_arguments() -> (Player, Player, bool) {
+fn get_number_from_argument(argument: Option<String>) -> u8 {
+  argument.unwrap().parse::<u8>().unwrap()
+}
+
+fn get_game(board: Board, index: Option<u64>, position: Option<String>) -> Game<Board> {
+  let game = if let Some(position) = position {
+    let mut game = match position.parse::<Game<Board>>() {
+      Ok(game) => game,
+      Err(error) => panic!("invalid position: {}", error),
+    };
+    // the notation only holds the board size, the rules come from the options
+    if board.toroidal {
+      game.rules = Board::toroidal(game.rules.width, game.rules.height, game.rules.k);
+    }
+
+    game.rules.misere = board.misere;
+    game.rules.gravity = board.gravity;
+    game
+  } else {
+    let grid = match index {
+      Some(index) => grid::get_grid_from_index(&board, index).expect("invalid index"),
+      None => grid::EMPTY,
+    };
+
+    Game::new(board, (grid, grid::get_turn(grid)))
+  };
+
+  if let Err(error) = grid::validate(&game.rules, game.state.0, game.state.1) {
+    panic!("invalid position: {}", error);
+  }
+
+  game
+}
+
+// options of a game besides its position
+struct Options {
+  x: PlayerId,
+  o: PlayerId,
+  ai_vs_ai: bool,
+  variant: String,
+  misere: bool,
+  random_turn: bool,
+  grids: u8,
+  // tags of the record describing the game, the players and the date aside
+  tags: Vec<(String, String)>,
+  // record of the moves to replay before playing
+  record: Option<Record>,
+  // file the record of the game is written to
+  save: Option<String>,
+}
+
+fn parse_arguments() -> (Options, Game<Board>) {
   const DEFAULT_PLAYER_ID: PlayerId = MCTS;
   let mut is_user_o = false;
   let mut ai_vs_ai = false;
   let mut ai = DEFAULT_PLAYER_ID;
   let mut ai_vs_ai_x = DEFAULT_PLAYER_ID;
   let mut ai_vs_ai_o = DEFAULT_PLAYER_ID;
+  let mut board = Board::classic();
+  let mut index = None;
+  let mut position = None;
+  let mut variant = String::from("classic");
+  let mut rules = Vec::new();
+  let mut grids = 3;
+  let mut record = None;
+  let mut save = None;
   let mut arguments = std::env::args().skip(1);
 
   while let Some(argument) = arguments.next() {
@@ -70,38 +156,210 @@ fn parse_arguments() -> (Player, Player, bool) {
         ai_vs_ai_x = get_player_id_from_argument(arguments.next());
         ai_vs_ai_o = get_player_id_from_argument(arguments.next());
       }
+      "-b" => {
+        let width = get_number_from_argument(arguments.next());
+        let height = get_number_from_argument(arguments.next());
+        let k = get_number_from_argument(arguments.next());
+        board = Board::new(width, height, k);
+      }
+      "-i" => index = Some(arguments.next().unwrap().parse::<u64>().unwrap()),
+      "-p" => position = arguments.next(),
+      "-n" => grids = get_number_from_argument(arguments.next()),
+      "-v" => variant = arguments.next().unwrap(),
+      "-r" => rules.push(arguments.next().unwrap()),
+      "-s" => save = arguments.next(),
+      // the tags of the record take the place of the options they stand for
+      "-l" => {
+        let text = std::fs::read_to_string(arguments.next().unwrap()).expect("cannot read record");
+        let loaded = match text.parse::<Record>() {
+          Ok(loaded) => loaded,
+          Err(error) => panic!("invalid record: {}", error),
+        };
+
+        if let Some(name) = loaded.get_tag("Variant") {
+          variant = name.to_string();
+        }
+
+        // a record without rules was played without any
+        rules = loaded
+          .get_tag("Rules")
+          .unwrap_or("")
+          .split_whitespace()
+          .map(String::from)
+          .collect();
+
+        if let Some(size) = loaded.get_tag("Board") {
+          let numbers: Vec<u8> = size
+            .split_whitespace()
+            .map(|number| number.parse::<u8>().expect("invalid board in record"))
+            .collect();
+
+          match numbers[..] {
+            [width, height, k] => board = Board::new(width, height, k),
+            _ => panic!("invalid board in record"),
+          }
+        }
+
+        if let Some(number) = loaded.get_tag("Grids") {
+          grids = number.parse::<u8>().expect("invalid grids in record");
+        }
+
+        position = loaded.get_tag("Position").map(String::from);
+        record = Some(loaded);
+      }
       _ => {}
     }
   }
 
-  if ai_vs_ai {
-    if is_ai_player(ai_vs_ai_x) == false || is_ai_player(ai_vs_ai_o) == false {
+  let mut misere = false;
+  let mut gravity = false;
+  let mut random_turn = false;
+  let mut toroidal = false;
+  rules.sort();
+  rules.dedup();
+
+  for rule in &rules {
+    match rule.as_str() {
+      "misere" => misere = true,
+      "gravity" => gravity = true,
+      "random" => random_turn = true,
+      "torus" => toroidal = true,
+      _ => panic!("invalid rule"),
+    }
+  }
+
+  if toroidal {
+    board = Board::toroidal(board.width, board.height, board.k);
+  }
+
+  // the other variants start from their empty board, and the notation and its
+  // validation follow the alternating moves of the classic game
+  if (index.is_some() || position.is_some()) && variant != "classic" {
+    panic!("positions are only supported in the classic variant");
+  }
+
+  board.misere = misere;
+  board.gravity = gravity;
+  let game = get_game(board, index, position);
+  let mut tags = vec![(String::from("Variant"), variant.clone())];
+
+  if !rules.is_empty() {
+    tags.push((String::from("Rules"), rules.join(" ")));
+  }
+
+  match variant.as_str() {
+    "classic" | "wild" => {
+      let board = &game.rules;
+      tags.push((
+        String::from("Board"),
+        format!("{} {} {}", board.width, board.height, board.k),
+      ));
+    }
+    "notakto" => tags.push((String::from("Grids"), grids.to_string())),
+    _ => {}
+  }
+
+  // only the classic game starts from a position
+  if variant == "classic" && game.state.0 != grid::EMPTY {
+    tags.push((String::from("Position"), game.to_string()));
+  }
+
+  let (x, o) = if ai_vs_ai {
+    if !is_ai_player(ai_vs_ai_x) || !is_ai_player(ai_vs_ai_o) {
       panic!("invalid ai");
     }
 
-    (get_player(ai_vs_ai_x), get_player(ai_vs_ai_o), ai_vs_ai)
+    (ai_vs_ai_x, ai_vs_ai_o)
+  } else if is_user_o {
+    (ai, USER)
   } else {
-    let user = get_player(USER);
-    let ai = get_player(ai);
+    (USER, ai)
+  };
 
-    if is_user_o {
-      (ai, user, ai_vs_ai)
-    } else {
-      (user, ai, ai_vs_ai)
-    }
+  if random_turn && (x == MINIMAX || o == MINIMAX) {
+    panic!("minimax does not play games of chance, expectimax does");
   }
+
+  (
+    Options {
+      x,
+      o,
+      ai_vs_ai,
+      variant,
+      misere,
+      random_turn,
+      grids,
+      tags,
+      record,
+      save,
+    },
+    game,
+  )
 }
 
-fn main() {
-  let (player_x, player_o, ai_vs_ai) = parse_arguments();
+fn play<R: Rules>(rules: R, state: R::State, options: &Options) {
+  let mut game = Game::new(rules, state);
+
+  if let Some(record) = &options.record {
+    if let Err(error) = record.replay(&mut game) {
+      panic!("invalid record: {}", error);
+    }
+  }
+
+  let mut player_x = get_player::<R>(options.x);
+  let mut player_o = get_player::<R>(options.o);
   println!(
-    "player x: {} (id: {})\nplayer o: {} (id: {})",
-    player_x.1, player_x.2, player_o.1, player_o.2
+    "player {}: {} (id: {})\nplayer {}: {} (id: {})",
+    game.rules.get_side_name(game::Player::X),
+    player_x.get_name(),
+    options.x,
+    game.rules.get_side_name(game::Player::O),
+    player_o.get_name(),
+    options.o
   );
 
-  if ai_vs_ai {
-    game::ai_vs_ai(player_x.0, player_o.0);
-  } else {
-    game::play(player_x.0, player_o.0);
+  if options.ai_vs_ai {
+    // the matches start from the position the record reached
+    let mut game = Game::new(game.rules, game.state);
+    game::ai_vs_ai(&mut game, player_x.as_mut(), player_o.as_mut());
+    return;
+  }
+
+  let mut tags = options.tags.clone();
+  tags.push((String::from("X"), player_x.get_name().to_string()));
+  tags.push((String::from("O"), player_o.get_name().to_string()));
+  tags.push((String::from("Date"), record::get_date()));
+  let save = |game: &Game<R>| {
+    if let Some(path) = &options.save {
+      std::fs::write(path, Record::new(&tags, game).to_string()).expect("cannot write record");
+    }
+  };
+  game::play(&mut game, player_x.as_mut(), player_o.as_mut(), &save);
+}
+
+fn main() {
+  let (options, game) = parse_arguments();
+  let (misere, grids) = (options.misere, options.grids);
+  // rules changing the board only apply to the variants played on it
+  let board_rules = game.rules.gravity || game.rules.toroidal;
+
+  match options.variant.as_str() {
+    "classic" if options.random_turn => play(
+      RandomTurn::new(game.rules),
+      (game.state.0, game.state.1, None),
+      &options,
+    ),
+    _ if options.random_turn => panic!("invalid variant or rule"),
+    "classic" => play(game.rules, game.state, &options),
+    "ultimate" if !misere && !board_rules => play(Ultimate::new(), ultimate::EMPTY, &options),
+    "qubic" if !board_rules => play(Qubic::new(misere), (grid::EMPTY, 0), &options),
+    "notakto" if !misere && !board_rules => play(Notakto::new(grids), (grid::EMPTY, 0), &options),
+    "numerical" if !misere && !board_rules => play(Numerical::new(), numerical::EMPTY, &options),
+    "quantum" if !misere && !board_rules => play(Quantum::new(), quantum::EMPTY, &options),
+    "gomoku" if !misere && !board_rules => play(Gomoku::new(), gomoku::EMPTY, &options),
+    "order" if !misere && !board_rules => play(Order::new(), (grid::EMPTY, 0), &options),
+    "morris" if !misere && !board_rules => play(Morris::new(), morris::EMPTY, &options),
+    "wild" => play(Wild::new(game.rules), game.state, &options),
+    _ => panic!("invalid variant or rule"),
   }
 }
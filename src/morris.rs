@@ -0,0 +1,245 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+};
+
+// three men's morris on the classic board, each player places three marks and
+// then every move slides one of their marks to an adjacent empty cell, along a
+// row, a column or one of the two long diagonals
+//
+// moves can be undone so games can cycle, a position reached for the third
+// time with the same player to move is a draw, and so is a game still going
+// after MAX_TURNS moves
+const MARKS: Turn = 3;
+const MAX_TURNS: usize = 60;
+
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+  grid: Grid,
+  turn: Turn,
+  // index of the grid after every move
+  history: [u16; MAX_TURNS + 1],
+}
+
+pub(crate) const EMPTY: State = State {
+  grid: grid::EMPTY,
+  turn: 0,
+  history: [0; MAX_TURNS + 1],
+};
+
+// cell the mark leaves, none while placing, and cell it goes to
+pub(crate) type Move = (Option<Cell>, Cell);
+
+pub(crate) struct Morris {
+  board: Board,
+  // cells adjacent to each cell
+  neighbours: [Bitboard; 9],
+}
+
+impl Morris {
+  pub(crate) fn new() -> Morris {
+    let mut neighbours = [0; 9];
+
+    for (cell, cells) in neighbours.iter_mut().enumerate() {
+      let (row, column) = ((cell / 3) as i32, (cell % 3) as i32);
+
+      for (row_step, column_step) in [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+      ] {
+        let (next_row, next_column) = (row + row_step, column + column_step);
+
+        // only the corners and the center lie on a diagonal
+        if !(0..3).contains(&next_row)
+          || !(0..3).contains(&next_column)
+          || row_step != 0 && column_step != 0 && (row + column) % 2 != 0
+        {
+          continue;
+        }
+
+        *cells |= 1 << (next_row * 3 + next_column);
+      }
+    }
+
+    Morris {
+      board: Board::classic(),
+      neighbours,
+    }
+  }
+
+  fn is_repeated(&self, state: &State) -> bool {
+    let current = state.history[state.turn as usize];
+
+    (0..=state.turn as usize)
+      .rev()
+      .step_by(2)
+      .filter(|turn| state.history[*turn] == current)
+      .count()
+      >= 3
+  }
+}
+
+impl Rules for Morris {
+  type State = State;
+  type Move = Move;
+  type Moves<'a> = std::vec::IntoIter<Move>;
+
+  fn get_moves(&self, state: &State) -> std::vec::IntoIter<Move> {
+    let empty = grid::get_empty_bits(&self.board, state.grid);
+
+    if state.turn < 2 * MARKS {
+      return grid::get_move_set(&self.board, empty)
+        .map(|cell| (None, cell))
+        .collect::<Vec<Move>>()
+        .into_iter();
+    }
+
+    let own = match game::get_player_from_turn(state.turn) {
+      Player::X => state.grid.x,
+      Player::O => state.grid.o,
+    };
+    let mut moves = Vec::with_capacity(8);
+
+    for from in grid::get_move_set(&self.board, own) {
+      for to in grid::get_move_set(&self.board, self.neighbours[from as usize] & empty) {
+        moves.push((Some(from), to));
+      }
+    }
+
+    moves.into_iter()
+  }
+
+  fn apply(&self, state: &State, (from, to): Move) -> State {
+    let player = game::get_player_from_turn(state.turn);
+    let mut next = *state;
+
+    if let Some(from) = from {
+      next.grid.x &= !(1 << from);
+      next.grid.o &= !(1 << from);
+    }
+
+    next.grid = grid::mark(next.grid, to, player);
+    next.turn += 1;
+    next.history[next.turn as usize] = grid::get_index(&self.board, next.grid) as u16;
+    next
+  }
+
+  // a player left without a move loses, the board never fills up so only
+  // the repetitions and the number of moves end the game in a draw
+  fn get_result(&self, state: &State) -> Result {
+    let is_complete = |marks: Bitboard| self.board.lines.iter().any(|line| marks & line == *line);
+
+    if is_complete(state.grid.x) {
+      Result::XWin
+    } else if is_complete(state.grid.o) {
+      Result::OWin
+    } else if state.turn as usize == MAX_TURNS || self.is_repeated(state) {
+      Result::Draw
+    } else if self.get_moves(state).len() > 0 {
+      Result::Continue
+    } else if game::get_player_from_turn(state.turn) == Player::X {
+      Result::OWin
+    } else {
+      Result::XWin
+    }
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    game::get_player_from_turn(state.turn)
+  }
+
+  fn print(&self, state: &State) {
+    grid::print(&self.board, state.grid);
+  }
+
+  fn get_move_name(&self, _state: &State, (from, to): Move) -> String {
+    let to = grid::get_cell_name(&self.board, to);
+
+    match from {
+      Some(from) => grid::get_cell_name(&self.board, from) + &to,
+      None => to,
+    }
+  }
+
+  // the cell on the numpad while placing, then the cell the mark leaves
+  // followed by the cell it goes to, e.g. 58 slides the mark of the bottom
+  // edge to the center
+  fn parse_move(&self, state: &State, input: &str) -> Option<Move> {
+    if !input.is_ascii() {
+      return None;
+    }
+
+    let m = match input.len() {
+      1 => (None, grid::get_cell_from_name(&self.board, input)?),
+      2 => (
+        Some(grid::get_cell_from_name(&self.board, &input[..1])?),
+        grid::get_cell_from_name(&self.board, &input[1..])?,
+      ),
+      _ => return None,
+    };
+
+    self.get_moves(state).find(|legal| *legal == m)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn replay(morris: &Morris, moves: &[&str]) -> State {
+    moves.iter().fold(EMPTY, |state, input| {
+      let m = morris.parse_move(&state, input).unwrap();
+      morris.apply(&state, m)
+    })
+  }
+
+  #[test]
+  fn game_without_line_goes_on_after_the_board_holds_nine_moves() {
+    let morris = Morris::new();
+    let state = replay(&morris, &["7", "8", "9", "4", "6", "2", "63", "41", "36"]);
+
+    assert!(morris.get_result(&state) == Result::Continue);
+    assert!(morris.parse_move(&state, "14").is_some());
+  }
+
+  #[test]
+  fn completed_line_wins() {
+    let morris = Morris::new();
+    let state = replay(&morris, &["7", "4", "8", "5", "9"]);
+
+    assert!(morris.get_result(&state) == Result::XWin);
+  }
+
+  #[test]
+  fn third_repetition_is_a_draw() {
+    let morris = Morris::new();
+    let state = replay(
+      &morris,
+      &[
+        "7", "8", "9", "4", "6", "2", "63", "41", "36", "14", "63", "41", "36", "14",
+      ],
+    );
+
+    assert!(morris.get_result(&state) == Result::Draw);
+  }
+}
@@ -0,0 +1,158 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+};
+
+// both players place x on a row of classic grids, a grid is dead once it holds
+// three in a row and whoever kills the last grid loses, cell = grid * 9 + cell
+// of the grid, the grids are laid out as the rows of a 3x(3 * grids) board
+pub(crate) struct Notakto {
+  board: Board,
+  grid: Board,
+  grids: u8,
+}
+
+impl Notakto {
+  pub(crate) fn new(grids: u8) -> Notakto {
+    if grids == 0 || grids as u32 * 9 > Bitboard::BITS {
+      panic!("invalid number of grids");
+    }
+
+    let grid = Board::classic();
+    let lines = (0..grids)
+      .flat_map(|b| grid.lines.iter().map(move |line| line << (b * 9)))
+      .collect();
+
+    Notakto {
+      board: Board::with_lines(3, grids * 3, 3, lines),
+      grid,
+      grids,
+    }
+  }
+
+  // cells of the grids holding three in a row
+  fn get_dead(&self, state: &(Grid, Turn)) -> Bitboard {
+    self
+      .board
+      .lines
+      .iter()
+      .filter(|line| state.0.x & *line == **line)
+      .fold(0, |dead, line| {
+        dead | 0b111_111_111 << (line.trailing_zeros() / 9 * 9)
+      })
+  }
+}
+
+impl Rules for Notakto {
+  type State = (Grid, Turn);
+  type Move = Cell;
+  type Moves<'a> = grid::MoveSet<'a>;
+
+  fn get_moves<'a>(&'a self, state: &(Grid, Turn)) -> grid::MoveSet<'a> {
+    grid::get_move_set(
+      &self.board,
+      grid::get_empty_bits(&self.board, state.0) & !self.get_dead(state),
+    )
+  }
+
+  fn apply(&self, state: &(Grid, Turn), cell: Cell) -> (Grid, Turn) {
+    (grid::mark(state.0, cell, Player::X), state.1 + 1)
+  }
+
+  // a live grid always has an empty cell, so the game ends exactly when the
+  // last grid dies and the player to move wins
+  fn get_result(&self, state: &(Grid, Turn)) -> Result {
+    if self.get_moves(state).len() > 0 {
+      Result::Continue
+    } else if game::get_player_from_turn(state.1) == Player::X {
+      Result::XWin
+    } else {
+      Result::OWin
+    }
+  }
+
+  fn get_player(&self, state: &(Grid, Turn)) -> Player {
+    game::get_player_from_turn(state.1)
+  }
+
+  // grids side by side, marks of dead grids are printed in capitals
+  fn print(&self, state: &(Grid, Turn)) {
+    let dead = self.get_dead(state);
+    let mut text = String::from("\n");
+
+    for b in 0..self.grids {
+      if b > 0 {
+        text.push_str("  ");
+      }
+
+      text.push_str(&format!("grid {}", b + 1));
+    }
+
+    for row in 0..3 {
+      text.push('\n');
+
+      for b in 0..self.grids {
+        if b > 0 {
+          text.push(' ');
+        }
+
+        text.push('|');
+
+        for column in 0..3 {
+          let cell = b * 9 + row * 3 + column;
+          let mark = if state.0.x & 1 << cell != 0 { 'x' } else { ' ' };
+
+          if column > 0 {
+            text.push(' ');
+          }
+
+          text.push(if dead & 1 << cell != 0 {
+            mark.to_ascii_uppercase()
+          } else {
+            mark
+          });
+        }
+
+        text.push('|');
+      }
+    }
+
+    println!("{}", text);
+  }
+
+  fn get_move_name(&self, _state: &(Grid, Turn), cell: Cell) -> String {
+    format!(
+      "{}{}",
+      cell / 9 + 1,
+      grid::get_cell_name(&self.grid, cell % 9)
+    )
+  }
+
+  // grid number followed by the cell on the numpad, e.g. 25 is the center of
+  // the second grid
+  fn parse_move(&self, state: &(Grid, Turn), input: &str) -> Option<Cell> {
+    let mut characters = input.chars();
+    let b = characters.next()?.to_digit(10)?;
+
+    if b == 0 || b > self.grids as u32 {
+      return None;
+    }
+
+    let cell = (b as Cell - 1) * 9 + grid::get_cell_from_name(&self.grid, characters.as_str())?;
+    self.get_moves(state).find(|legal| *legal == cell)
+  }
+}
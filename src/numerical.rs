@@ -0,0 +1,187 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+};
+
+// x writes the odd numbers from 1 to 9 and o the even ones, each number once,
+// and whoever completes a full line summing to 15 wins
+
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+  // number written in each cell, 0 when empty
+  numbers: [u8; 9],
+  filled: Bitboard,
+  // one bit per number already written
+  used: u16,
+  turn: Turn,
+}
+
+pub(crate) const EMPTY: State = State {
+  numbers: [0; 9],
+  filled: 0,
+  used: 0,
+  turn: 0,
+};
+
+// cell and number written in it
+pub(crate) type Move = (Cell, u8);
+
+pub(crate) struct Numerical {
+  board: Board,
+}
+
+impl Numerical {
+  pub(crate) fn new() -> Numerical {
+    Numerical {
+      board: Board::classic(),
+    }
+  }
+}
+
+fn get_empty_cells<'a>(board: &'a Board, state: &State) -> grid::MoveSet<'a> {
+  grid::get_empty_cells(
+    board,
+    Grid {
+      x: state.filled,
+      o: 0,
+    },
+  )
+}
+
+// numbers of the player to move not written yet
+fn get_numbers(state: &State) -> impl Iterator<Item = u8> + Clone {
+  let first = if game::get_player_from_turn(state.turn) == Player::X {
+    1
+  } else {
+    2
+  };
+  let used = state.used;
+
+  (first..10)
+    .step_by(2)
+    .filter(move |number| used & 1 << number == 0)
+}
+
+impl Rules for Numerical {
+  type State = State;
+  type Move = Move;
+  type Moves<'a> = std::vec::IntoIter<Move>;
+
+  fn get_moves(&self, state: &State) -> std::vec::IntoIter<Move> {
+    let mut moves = Vec::with_capacity(45);
+
+    for cell in get_empty_cells(&self.board, state) {
+      for number in get_numbers(state) {
+        moves.push((cell, number));
+      }
+    }
+
+    moves.into_iter()
+  }
+
+  fn apply(&self, state: &State, (cell, number): Move) -> State {
+    let mut next = *state;
+    next.numbers[cell as usize] = number;
+    next.filled |= 1 << cell;
+    next.used |= 1 << number;
+    next.turn += 1;
+    next
+  }
+
+  fn get_random_move(&self, state: &State) -> Move {
+    use rand::Rng;
+
+    let mut numbers = get_numbers(state);
+    let n = rand::thread_rng().gen_range(0..numbers.clone().count());
+
+    (
+      get_empty_cells(&self.board, state).choose().unwrap(),
+      numbers.nth(n).unwrap(),
+    )
+  }
+
+  // the player who made the last move completed the line
+  fn get_result(&self, state: &State) -> Result {
+    let completed = self.board.lines.iter().any(|line| {
+      state.filled & line == *line
+        && grid::get_move_set(&self.board, *line)
+          .map(|cell| state.numbers[cell as usize])
+          .sum::<u8>()
+          == 15
+    });
+
+    if completed {
+      match game::get_player_from_turn(state.turn - 1) {
+        Player::X => Result::XWin,
+        Player::O => Result::OWin,
+      }
+    } else if get_empty_cells(&self.board, state).len() == 0 {
+      Result::Draw
+    } else {
+      Result::Continue
+    }
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    game::get_player_from_turn(state.turn)
+  }
+
+  fn print(&self, state: &State) {
+    let mut text = String::new();
+
+    for row in 0..3 {
+      text.push_str("\n|");
+
+      for column in 0..3 {
+        if column > 0 {
+          text.push(' ');
+        }
+
+        text.push(match state.numbers[row * 3 + column] {
+          0 => ' ',
+          number => (b'0' + number) as char,
+        });
+      }
+
+      text.push('|');
+    }
+
+    println!("{}", text);
+
+    if self.get_result(state) == Result::Continue {
+      let numbers: Vec<String> = get_numbers(state)
+        .map(|number| number.to_string())
+        .collect();
+      println!("numbers left: {}", numbers.join(" "));
+    }
+  }
+
+  fn get_move_name(&self, _state: &State, (cell, number): Move) -> String {
+    format!("{}{}", grid::get_cell_name(&self.board, cell), number)
+  }
+
+  // cell on the numpad followed by the number, e.g. 59 writes 9 in the center
+  fn parse_move(&self, state: &State, input: &str) -> Option<Move> {
+    if !input.is_ascii() || input.len() != 2 {
+      return None;
+    }
+
+    let cell = grid::get_cell_from_name(&self.board, &input[..1])?;
+    let number = input[1..].parse::<u8>().ok()?;
+    self.get_moves(state).find(|legal| *legal == (cell, number))
+  }
+}
@@ -0,0 +1,118 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  ai::{self, Depth, Score},
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Board, Grid},
+  wild::{self, Move},
+};
+
+// order and chaos on a 6x6 board, both players write either symbol as in wild
+// tic-tac-toe, order wins with five of a symbol in a row and chaos wins when
+// the board is filled without one, x plays order and o plays chaos
+pub(crate) struct Order {
+  board: Board,
+}
+
+impl Order {
+  pub(crate) fn new() -> Order {
+    Order {
+      board: Board::new(6, 6, 5),
+    }
+  }
+}
+
+impl Rules for Order {
+  type State = (Grid, Turn);
+  type Move = Move;
+  type Moves<'a> = wild::Moves<'a>;
+
+  fn get_moves<'a>(&'a self, state: &(Grid, Turn)) -> wild::Moves<'a> {
+    wild::get_moves(&self.board, state.0)
+  }
+
+  fn apply(&self, state: &(Grid, Turn), (cell, symbol): Move) -> (Grid, Turn) {
+    (grid::mark(state.0, cell, symbol), state.1 + 1)
+  }
+
+  fn get_random_move(&self, state: &(Grid, Turn)) -> Move {
+    wild::get_random_move(&self.board, state.0)
+  }
+
+  // the result depends on the roles and not on who wrote the symbols
+  fn get_result(&self, state: &(Grid, Turn)) -> Result {
+    let completed = self
+      .board
+      .lines
+      .iter()
+      .any(|line| state.0.x & line == *line || state.0.o & line == *line);
+
+    if completed {
+      Result::XWin
+    } else if grid::get_empty_cells(&self.board, state.0).len() == 0 {
+      Result::OWin
+    } else {
+      Result::Continue
+    }
+  }
+
+  fn get_player(&self, state: &(Grid, Turn)) -> Player {
+    game::get_player_from_turn(state.1)
+  }
+
+  fn get_side_name(&self, player: Player) -> &'static str {
+    match player {
+      Player::X => "order",
+      Player::O => "chaos",
+    }
+  }
+
+  fn get_search_depth(&self) -> Depth {
+    3
+  }
+
+  // lines holding a single symbol are chances for order, weighted by their
+  // marks, and lines holding both are lost to chaos
+  fn evaluate(&self, state: &(Grid, Turn)) -> Score {
+    const WEIGHTS: [Score; 5] = [0, 0, 1, 4, 16];
+    let mut score = 0;
+
+    for line in &self.board.lines {
+      let x = (state.0.x & line).count_ones() as usize;
+      let o = (state.0.o & line).count_ones() as usize;
+
+      if x > 0 && o > 0 {
+        score -= 2;
+      } else {
+        score += WEIGHTS[x + o];
+      }
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  fn print(&self, state: &(Grid, Turn)) {
+    grid::print(&self.board, state.0);
+  }
+
+  fn get_move_name(&self, _state: &(Grid, Turn), m: Move) -> String {
+    wild::get_move_name(&self.board, m)
+  }
+
+  // cell followed by the symbol, e.g. c3x writes x in the cell c3
+  fn parse_move(&self, state: &(Grid, Turn), input: &str) -> Option<Move> {
+    wild::parse_move(&self.board, state.0, input)
+  }
+}
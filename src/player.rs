@@ -0,0 +1,48 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::game::{Game, Rules};
+
+// what a player does on its turn, taking back moves or playing them again
+// goes back or forward to its previous or next turn
+pub(crate) enum Action<M> {
+  Move(M),
+  Undo,
+  Redo,
+}
+
+// one side of a game, told about every move, every undo and redo and the end of
+// the game so it can keep state between its moves, the game holds the moves
+// played so far
+pub(crate) trait Player<R: Rules> {
+  fn get_name(&self) -> &'static str;
+
+  fn new_game(&mut self, _game: &Game<R>) {}
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move;
+
+  // players able to take back moves ask for it instead of a move
+  fn choose_action(&mut self, game: &Game<R>) -> Action<R::Move> {
+    Action::Move(self.choose_move(game))
+  }
+
+  // move of the opponent, already played in the game
+  fn opponent_moved(&mut self, _game: &Game<R>, _m: R::Move) {}
+
+  // moves taken back or played again by undo and redo, the game holds the
+  // moves played since its start
+  fn history_changed(&mut self, _game: &Game<R>) {}
+
+  fn game_over(&mut self, _game: &Game<R>) {}
+}
@@ -0,0 +1,405 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+};
+
+// every move places a spooky mark in two cells of the classic board, the
+// spooky marks are the edges of an entanglement graph between the cells, and
+// once a mark closes a cycle the opponent chooses the cell it collapses into,
+// which forces every other mark of the cycle and the marks hanging from it
+// into a classical mark in one of their cells
+//
+// the subscript of a mark is its move number, odd for x and even for o, there
+// are at most 9 marks as every classical cell holds one mark and the spooky
+// marks left form a forest on the other cells
+
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+  // cells of the marks by subscript - 1
+  marks: [(Cell, Cell); 9],
+  // subscript of the classical mark of each cell, 0 when not collapsed
+  classical: [u8; 9],
+  // one bit per collapsed mark
+  collapsed: u16,
+  count: u8,
+  // mark closing a cycle, waiting for the opponent to collapse it
+  cycle: Option<u8>,
+}
+
+pub(crate) const EMPTY: State = State {
+  marks: [(0, 0); 9],
+  classical: [0; 9],
+  collapsed: 0,
+  count: 0,
+  cycle: None,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Move {
+  Spooky(Cell, Cell),
+  // cell the mark closing the cycle collapses into
+  Collapse(Cell),
+  // classical mark in the last cell left
+  Classical(Cell),
+}
+
+pub(crate) struct Quantum {
+  board: Board,
+}
+
+impl Quantum {
+  pub(crate) fn new() -> Quantum {
+    Quantum {
+      board: Board::classic(),
+    }
+  }
+
+  fn get_free_cells(&self, state: &State) -> grid::MoveSet<'_> {
+    let classical = (0..9)
+      .filter(|cell| state.classical[*cell] != 0)
+      .fold(0, |cells, cell| cells | 1 << cell);
+
+    grid::get_empty_cells(&self.board, Grid { x: classical, o: 0 })
+  }
+
+  // lowest maximum subscript among the lines of a player, the line completed
+  // first wins when both players get lines in the same collapse
+  fn get_first_line(&self, state: &State, player: Player) -> Option<u8> {
+    let parity = if player == Player::X { 1 } else { 0 };
+    let cells: Bitboard = (0..9)
+      .filter(|cell| state.classical[*cell] != 0 && state.classical[*cell] % 2 == parity)
+      .fold(0, |cells, cell| cells | 1 << cell);
+
+    self
+      .board
+      .lines
+      .iter()
+      .filter(|line| cells & *line == **line)
+      .map(|line| {
+        grid::get_move_set(&self.board, *line)
+          .map(|cell| state.classical[cell as usize])
+          .max()
+          .unwrap()
+      })
+      .min()
+  }
+}
+
+fn is_live(state: &State, mark: u8) -> bool {
+  mark < state.count && state.collapsed & 1 << mark == 0
+}
+
+// cells reachable from a cell through the spooky marks
+fn get_entangled(state: &State, cell: Cell) -> Bitboard {
+  let mut reached: Bitboard = 1 << cell;
+
+  loop {
+    let before = reached;
+
+    for mark in (0..state.count).filter(|mark| is_live(state, *mark)) {
+      let (a, b) = state.marks[mark as usize];
+
+      if reached & (1 << a | 1 << b) != 0 {
+        reached |= 1 << a | 1 << b;
+      }
+    }
+
+    if reached == before {
+      return reached;
+    }
+  }
+}
+
+// every mark sharing a cell with a collapsed mark collapses into its other
+// cell
+fn collapse(state: &mut State, mark: u8, cell: Cell) {
+  let mut pending = vec![(mark, cell)];
+
+  while let Some((mark, cell)) = pending.pop() {
+    if !is_live(state, mark) {
+      continue;
+    }
+
+    state.classical[cell as usize] = mark + 1;
+    state.collapsed |= 1 << mark;
+
+    for other in (0..state.count).filter(|other| is_live(state, *other)) {
+      match state.marks[other as usize] {
+        (a, b) if a == cell => pending.push((other, b)),
+        (a, b) if b == cell => pending.push((other, a)),
+        _ => {}
+      }
+    }
+  }
+}
+
+fn get_symbol(mark: u8) -> char {
+  if mark.is_multiple_of(2) {
+    'x'
+  } else {
+    'o'
+  }
+}
+
+impl Rules for Quantum {
+  type State = State;
+  type Move = Move;
+  type Moves<'a> = std::vec::IntoIter<Move>;
+
+  fn get_moves(&self, state: &State) -> std::vec::IntoIter<Move> {
+    if let Some(mark) = state.cycle {
+      let (a, b) = state.marks[mark as usize];
+      return vec![Move::Collapse(a), Move::Collapse(b)].into_iter();
+    }
+
+    let free = self.get_free_cells(state);
+
+    if free.len() == 1 {
+      return vec![Move::Classical(free.first().unwrap())].into_iter();
+    }
+
+    let mut moves = Vec::with_capacity(36);
+
+    for a in free {
+      for b in free.filter(|b| *b > a) {
+        moves.push(Move::Spooky(a, b));
+      }
+    }
+
+    moves.into_iter()
+  }
+
+  fn apply(&self, state: &State, m: Move) -> State {
+    let mut next = *state;
+
+    match m {
+      Move::Spooky(a, b) => {
+        if get_entangled(state, a) & 1 << b != 0 {
+          next.cycle = Some(state.count);
+        }
+
+        next.marks[state.count as usize] = (a, b);
+        next.count += 1;
+      }
+      Move::Collapse(cell) => {
+        collapse(&mut next, state.cycle.unwrap(), cell);
+        next.cycle = None;
+      }
+      Move::Classical(cell) => {
+        next.marks[state.count as usize] = (cell, cell);
+        next.count += 1;
+        collapse(&mut next, state.count, cell);
+      }
+    }
+
+    next
+  }
+
+  fn get_result(&self, state: &State) -> Result {
+    match (
+      self.get_first_line(state, Player::X),
+      self.get_first_line(state, Player::O),
+    ) {
+      (Some(x), Some(o)) if x < o => Result::XWin,
+      (Some(_), Some(_)) => Result::OWin,
+      (Some(_), None) => Result::XWin,
+      (None, Some(_)) => Result::OWin,
+      (None, None) if state.cycle.is_none() && self.get_free_cells(state).len() == 0 => {
+        Result::Draw
+      }
+      (None, None) => Result::Continue,
+    }
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    game::get_player_from_turn(state.count as Turn)
+  }
+
+  // each cell holds a slot per subscript, spooky marks are printed in their
+  // slot and a classical mark in capitals in the middle of the cell
+  fn print(&self, state: &State) {
+    let mut text = String::new();
+
+    for row in 0..9 {
+      if row % 3 == 0 {
+        text.push_str("\n|--------+--------+--------|");
+      }
+
+      text.push_str("\n|");
+
+      for column in 0..9 {
+        let cell = (row / 3 * 3 + column / 3) as Cell;
+        let slot = row % 3 * 3 + column % 3;
+        let classical = state.classical[cell as usize];
+
+        let mark = if classical != 0 {
+          if slot == 4 {
+            Some((get_symbol(classical - 1).to_ascii_uppercase(), classical))
+          } else {
+            None
+          }
+        } else if is_live(state, slot) {
+          let (a, b) = state.marks[slot as usize];
+
+          if a == cell || b == cell {
+            Some((get_symbol(slot), slot + 1))
+          } else {
+            None
+          }
+        } else {
+          None
+        };
+
+        match mark {
+          Some((symbol, subscript)) => text.push_str(&format!("{}{}", symbol, subscript)),
+          None => text.push_str("  "),
+        }
+
+        text.push(if column % 3 == 2 { '|' } else { ' ' });
+      }
+    }
+
+    text.push_str("\n|--------+--------+--------|");
+    println!("{}", text);
+
+    if let Some(mark) = state.cycle {
+      let (a, b) = state.marks[mark as usize];
+      println!(
+        "{}{} closed a cycle, collapse it into {} or {}",
+        get_symbol(mark),
+        mark + 1,
+        grid::get_cell_name(&self.board, a),
+        grid::get_cell_name(&self.board, b)
+      );
+    }
+  }
+
+  fn get_move_name(&self, _state: &State, m: Move) -> String {
+    match m {
+      Move::Spooky(a, b) => {
+        grid::get_cell_name(&self.board, a) + &grid::get_cell_name(&self.board, b)
+      }
+      Move::Collapse(cell) | Move::Classical(cell) => grid::get_cell_name(&self.board, cell),
+    }
+  }
+
+  // two numpad digits for a spooky mark, e.g. 13 is a mark in both bottom
+  // corners, and a single digit for the cell of a collapse or of the last
+  // classical mark
+  fn parse_move(&self, state: &State, input: &str) -> Option<Move> {
+    if !input.is_ascii() {
+      return None;
+    }
+
+    let m = match input.len() {
+      1 => {
+        let cell = grid::get_cell_from_name(&self.board, input)?;
+
+        if state.cycle.is_some() {
+          Move::Collapse(cell)
+        } else {
+          Move::Classical(cell)
+        }
+      }
+      2 => {
+        let a = grid::get_cell_from_name(&self.board, &input[..1])?;
+        let b = grid::get_cell_from_name(&self.board, &input[1..])?;
+        Move::Spooky(std::cmp::min(a, b), std::cmp::max(a, b))
+      }
+      _ => return None,
+    };
+
+    self.get_moves(state).find(|legal| *legal == m)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn replay(quantum: &Quantum, moves: &[Move]) -> State {
+    moves
+      .iter()
+      .fold(EMPTY, |state, m| quantum.apply(&state, *m))
+  }
+
+  // every mark already collapsed, with the subscripts of the classical marks
+  fn get_collapsed(classical: [u8; 9]) -> State {
+    let count = classical.iter().filter(|mark| **mark != 0).count() as u8;
+
+    State {
+      classical,
+      collapsed: (1 << count) - 1,
+      count,
+      ..EMPTY
+    }
+  }
+
+  #[test]
+  fn collapse_forces_the_cycle_and_the_marks_hanging_from_it() {
+    let quantum = Quantum::new();
+    let state = replay(
+      &quantum,
+      &[
+        Move::Spooky(0, 1),
+        Move::Spooky(2, 5),
+        Move::Spooky(1, 2),
+        Move::Spooky(0, 2),
+      ],
+    );
+
+    assert_eq!(state.cycle, Some(3));
+    assert!(
+      quantum.get_moves(&state).collect::<Vec<_>>() == [Move::Collapse(0), Move::Collapse(2)]
+    );
+
+    let state = quantum.apply(&state, Move::Collapse(0));
+
+    assert_eq!(state.cycle, None);
+    assert_eq!(state.classical, [4, 1, 3, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(state.collapsed, 0b1111);
+    assert!(quantum.get_player(&state) == Player::X);
+  }
+
+  #[test]
+  fn line_with_the_lower_highest_subscript_wins() {
+    let quantum = Quantum::new();
+
+    // x completes the top row with its fifth mark, o the middle row only with
+    // its eighth
+    let state = get_collapsed([1, 3, 5, 2, 4, 8, 7, 6, 0]);
+    assert!(quantum.get_result(&state) == Result::XWin);
+
+    let state = get_collapsed([1, 3, 9, 2, 4, 6, 7, 5, 8]);
+    assert!(quantum.get_result(&state) == Result::OWin);
+  }
+
+  #[test]
+  fn last_free_cell_takes_a_classical_mark() {
+    let quantum = Quantum::new();
+    let state = get_collapsed([1, 2, 3, 5, 4, 6, 8, 7, 0]);
+
+    assert!(quantum.get_moves(&state).collect::<Vec<_>>() == [Move::Classical(8)]);
+    assert!(quantum.get_result(&state) == Result::Continue);
+
+    let state = quantum.apply(&state, Move::Classical(8));
+
+    assert_eq!(state.classical[8], 9);
+    assert_eq!(state.count, 9);
+    assert!(quantum.get_result(&state) == Result::Draw);
+  }
+}
@@ -0,0 +1,225 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  ai::{self, Depth, Score},
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+  tactics,
+};
+
+const DEPTH: Depth = 4;
+
+// four layers of 4x4 cells stacked from the bottom, cell = layer * 16 + cell of
+// the layer, the layers are laid out as the rows of a 4x16 board
+pub(crate) struct Qubic {
+  board: Board,
+  layer: Board,
+}
+
+// the 76 lines of four cells along the 13 directions of the cube
+fn get_lines() -> Vec<Bitboard> {
+  let mut lines = Vec::with_capacity(76);
+
+  for (dx, dy, dz) in [
+    (1, 0, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (1, 1, 0),
+    (1, -1, 0),
+    (1, 0, 1),
+    (1, 0, -1),
+    (0, 1, 1),
+    (0, 1, -1),
+    (1, 1, 1),
+    (1, 1, -1),
+    (1, -1, 1),
+    (1, -1, -1),
+  ] {
+    for z in 0..4 {
+      for y in 0..4 {
+        for x in 0..4 {
+          let end = (x + 3 * dx, y + 3 * dy, z + 3 * dz);
+
+          if !(0..4).contains(&end.0) || !(0..4).contains(&end.1) || !(0..4).contains(&end.2) {
+            continue;
+          }
+
+          let mut line = 0;
+
+          for i in 0..4 {
+            line |= 1 << ((z + i * dz) * 16 + (y + i * dy) * 4 + x + i * dx);
+          }
+
+          lines.push(line);
+        }
+      }
+    }
+  }
+
+  lines
+}
+
+impl Qubic {
+  pub(crate) fn new(misere: bool) -> Qubic {
+    let mut board = Board::with_lines(4, 16, 4, get_lines());
+    board.misere = misere;
+
+    Qubic {
+      board,
+      layer: Board::new(4, 4, 4),
+    }
+  }
+}
+
+impl Rules for Qubic {
+  type State = (Grid, Turn);
+  type Move = Cell;
+  type Moves<'a> = grid::MoveSet<'a>;
+
+  fn get_moves<'a>(&'a self, state: &(Grid, Turn)) -> grid::MoveSet<'a> {
+    grid::get_empty_cells(&self.board, state.0)
+  }
+
+  fn apply(&self, state: &(Grid, Turn), cell: Cell) -> (Grid, Turn) {
+    (
+      grid::mark(state.0, cell, game::get_player_from_turn(state.1)),
+      state.1 + 1,
+    )
+  }
+
+  fn get_result(&self, state: &(Grid, Turn)) -> Result {
+    grid::get_result(&self.board, state.0, state.1)
+  }
+
+  fn get_player(&self, state: &(Grid, Turn)) -> Player {
+    game::get_player_from_turn(state.1)
+  }
+
+  fn get_random_move(&self, state: &(Grid, Turn)) -> Cell {
+    grid::get_random_empty_cell(&self.board, state.0)
+  }
+
+  // the cube holds far too many positions to be solved
+  fn get_search_depth(&self) -> Depth {
+    DEPTH
+  }
+
+  // a player to move with three in an open line wins, otherwise open lines
+  // count by the number of marks in them, in misère lines are to be avoided
+  // and the player to move is not about to win
+  fn evaluate(&self, state: &(Grid, Turn)) -> Score {
+    let x = tactics::analyze(&self.board, state.0, Player::X);
+    let o = tactics::analyze(&self.board, state.0, Player::O);
+
+    if !self.board.misere {
+      match game::get_player_from_turn(state.1) {
+        Player::X if x.wins != 0 => return ai::X_WIN - ai::DRAW / 2,
+        Player::O if o.wins != 0 => return ai::O_WIN + ai::DRAW / 2,
+        _ => {}
+      }
+    }
+
+    let mut score = 8 * (x.wins.count_ones() as Score - o.wins.count_ones() as Score)
+      + 2 * (x.open_twos as Score - o.open_twos as Score)
+      + (x.open_ones as Score - o.open_ones as Score) / 2;
+
+    if self.board.misere {
+      score = -score;
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  // the marks tell how many moves were played
+  fn get_key(&self, state: &(Grid, Turn)) -> Option<u64> {
+    Some(grid::get_key(&self.board, state.0))
+  }
+
+  // layers side by side from the bottom one on the left
+  fn print(&self, state: &(Grid, Turn)) {
+    let won = self
+      .board
+      .lines
+      .iter()
+      .filter(|line| state.0.x & *line == **line || state.0.o & *line == **line)
+      .fold(0, |cells, line| cells | line);
+    let mut text = String::from("\n layer 1   layer 2   layer 3   layer 4");
+
+    for row in 0..4 {
+      text.push('\n');
+
+      for layer in 0..4 {
+        if layer > 0 {
+          text.push(' ');
+        }
+
+        text.push('|');
+
+        for column in 0..4 {
+          let cell = layer * 16 + row * 4 + column;
+          let mark = if state.0.x & 1 << cell != 0 {
+            'x'
+          } else if state.0.o & 1 << cell != 0 {
+            'o'
+          } else {
+            ' '
+          };
+
+          if column > 0 {
+            text.push(' ');
+          }
+
+          text.push(if won & 1 << cell != 0 {
+            mark.to_ascii_uppercase()
+          } else {
+            mark
+          });
+        }
+
+        text.push('|');
+      }
+    }
+
+    println!("{}", text);
+  }
+
+  fn get_move_name(&self, _state: &(Grid, Turn), cell: Cell) -> String {
+    format!(
+      "{}{}",
+      cell / 16 + 1,
+      grid::get_cell_name(&self.layer, cell % 16)
+    )
+  }
+
+  // layer number followed by the cell of the layer, e.g. 1a1 is the bottom
+  // left cell of the bottom layer
+  fn parse_move(&self, state: &(Grid, Turn), input: &str) -> Option<Cell> {
+    let mut characters = input.chars();
+    let layer = characters.next()?.to_digit(10)?;
+
+    if !(1..=4).contains(&layer) {
+      return None;
+    }
+
+    let cell =
+      (layer as Cell - 1) * 16 + grid::get_cell_from_name(&self.layer, characters.as_str())?;
+
+    if grid::is_cell_empty(state.0, cell) {
+      Some(cell)
+    } else {
+      None
+    }
+  }
+}
@@ -0,0 +1,151 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  ai::{self, Depth, Score},
+  game::{Player, Result, Rules, Turn},
+  grid::{self, Board, Cell, Grid},
+  tactics,
+};
+
+// a coin flip decides who moves before every move, so the number of marks of
+// each player does not follow from the turn
+pub(crate) struct RandomTurn {
+  board: Board,
+}
+
+// the player to move is none until the coin is flipped
+pub(crate) type State = (Grid, Turn, Option<Player>);
+
+// largest board searched to the end, and the depth on larger ones, there is no
+// pruning across the coin flips
+const SOLVED_CELLS: usize = 9;
+const DEPTH: Depth = 4;
+
+impl RandomTurn {
+  pub(crate) fn new(board: Board) -> RandomTurn {
+    RandomTurn { board }
+  }
+}
+
+impl Rules for RandomTurn {
+  type State = State;
+  type Move = Cell;
+  type Moves<'a> = grid::MoveSet<'a>;
+
+  fn get_moves<'a>(&'a self, state: &State) -> grid::MoveSet<'a> {
+    grid::get_empty_cells(&self.board, state.0)
+  }
+
+  fn apply(&self, state: &State, cell: Cell) -> State {
+    (
+      grid::mark(state.0, cell, state.2.unwrap()),
+      state.1 + 1,
+      None,
+    )
+  }
+
+  fn get_random_move(&self, state: &State) -> Cell {
+    grid::get_random_empty_cell(&self.board, state.0)
+  }
+
+  fn get_result(&self, state: &State) -> Result {
+    grid::get_result(&self.board, state.0, state.1)
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    state.2.unwrap_or(Player::X)
+  }
+
+  fn get_chances(&self, state: &State) -> Option<Vec<(State, f32)>> {
+    match state.2 {
+      Some(_) => None,
+      None => Some(vec![
+        ((state.0, state.1, Some(Player::X)), 0.5),
+        ((state.0, state.1, Some(Player::O)), 0.5),
+      ]),
+    }
+  }
+
+  // small boards are solved, so the values of the positions are exact
+  fn get_search_depth(&self) -> Depth {
+    if self.board.number_cells() <= SOLVED_CELLS {
+      self.board.number_cells() as Depth
+    } else {
+      DEPTH
+    }
+  }
+
+  // the index of the grid with the side to move as one more base 3 digit, 0
+  // before the coin flip
+  fn get_key(&self, state: &State) -> Option<u64> {
+    if self.board.number_cells() >= 40 {
+      return None;
+    }
+
+    let side = match state.2 {
+      None => 0,
+      Some(Player::X) => 1,
+      Some(Player::O) => 2,
+    };
+
+    Some(grid::get_index(&self.board, state.0) * 3 + side)
+  }
+
+  // either player may move next, so the threats of both count the same
+  fn evaluate(&self, state: &State) -> Score {
+    let x = tactics::analyze(&self.board, state.0, Player::X);
+    let o = tactics::analyze(&self.board, state.0, Player::O);
+    let mut score = 4 * (x.wins.count_ones() as Score - o.wins.count_ones() as Score)
+      + x.open_twos as Score
+      - o.open_twos as Score;
+
+    if self.board.misere {
+      score = -score;
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  fn print(&self, state: &State) {
+    grid::print(&self.board, state.0);
+  }
+
+  // the side to move follows the cell, so the coin flips can be told from a
+  // record of the moves
+  fn get_move_name(&self, state: &State, cell: Cell) -> String {
+    let name = self.board.get_move_name(&(state.0, state.1), cell);
+
+    match state.2 {
+      Some(Player::O) => name + "o",
+      _ => name + "x",
+    }
+  }
+
+  // the cell as in the classic game, optionally followed by the side to move
+  fn parse_move(&self, state: &State, input: &str) -> Option<Cell> {
+    let player = self.get_player(state);
+    let input = match input.strip_suffix(['x', 'X']) {
+      Some(input) if player == Player::X => input,
+      Some(_) => return None,
+      None => match input.strip_suffix(['o', 'O']) {
+        Some(input) if player == Player::O => input,
+        Some(_) => return None,
+        None => input,
+      },
+    };
+
+    self.board.parse_move(&(state.0, state.1), input)
+  }
+}
@@ -0,0 +1,289 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::game::{Game, Player, Result, Rules};
+
+// a game record in the manner of pgn, tags in brackets followed by the moves
+// numbered by pairs, e.g.
+//
+// [Variant "classic"]
+// [X "user"]
+// [O "mcts"]
+// [Date "2023.05.01"]
+// [Result "draw"]
+//
+// 1. 5 1 2. 3 7 3. 4 6 4. 2 8 5. 9
+//
+// the result is "*" while the game goes on, a game started by o begins with
+// "1. ..." in place of the move of x
+pub(crate) struct Record {
+  pub(crate) tags: Vec<(String, String)>,
+  pub(crate) first: Player,
+  pub(crate) moves: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum RecordError {
+  InvalidTag(String),
+  InvalidMove { number: usize, text: String },
+}
+
+impl std::fmt::Display for RecordError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      RecordError::InvalidTag(line) => write!(f, "invalid tag '{}'", line),
+      RecordError::InvalidMove { number, text } => {
+        write!(f, "invalid move {} '{}'", number, text)
+      }
+    }
+  }
+}
+
+impl Record {
+  // the result and the moves are taken from the game
+  pub(crate) fn new<R: Rules>(tags: &[(String, String)], game: &Game<R>) -> Record {
+    let result = match game.get_result() {
+      Result::Continue => String::from("*"),
+      Result::Draw => String::from("draw"),
+      Result::XWin => format!("{} win", game.rules.get_side_name(Player::X)),
+      Result::OWin => format!("{} win", game.rules.get_side_name(Player::O)),
+    };
+    let mut tags = tags.to_vec();
+    tags.push((String::from("Result"), result));
+
+    Record {
+      tags,
+      first: game.rules.get_player(&game.start),
+      moves: game
+        .get_history()
+        .map(|(state, m)| game.rules.get_move_name(state, m))
+        .collect(),
+    }
+  }
+
+  pub(crate) fn get_tag(&self, name: &str) -> Option<&str> {
+    self
+      .tags
+      .iter()
+      .find(|(tag, _)| tag == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  // plays the moves in the game, a coin flip is settled by the outcome the
+  // next move can be played in
+  pub(crate) fn replay<R: Rules>(
+    &self,
+    game: &mut Game<R>,
+  ) -> std::result::Result<(), RecordError> {
+    for (number, text) in self.moves.iter().enumerate() {
+      let error = || RecordError::InvalidMove {
+        number: number + 1,
+        text: text.clone(),
+      };
+
+      if game.get_result() != Result::Continue {
+        return Err(error());
+      }
+
+      if let Some(chances) = game.rules.get_chances(&game.state) {
+        game.state = chances
+          .into_iter()
+          .map(|(state, _)| state)
+          .find(|state| game.rules.parse_move(state, text).is_some())
+          .ok_or_else(error)?;
+      }
+
+      let m = game.rules.parse_move(&game.state, text).ok_or_else(error)?;
+      game.play(m);
+    }
+
+    Ok(())
+  }
+}
+
+impl std::str::FromStr for Record {
+  type Err = RecordError;
+
+  fn from_str(text: &str) -> std::result::Result<Record, RecordError> {
+    let mut tags = Vec::new();
+    let mut first = Player::X;
+    let mut moves = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+      if let Some(tag) = line.strip_prefix('[') {
+        let (name, value) = tag
+          .strip_suffix(']')
+          .and_then(|tag| tag.split_once(' '))
+          .and_then(|(name, value)| Some((name, value.strip_prefix('"')?.strip_suffix('"')?)))
+          .ok_or_else(|| RecordError::InvalidTag(line.to_string()))?;
+        tags.push((name.to_string(), value.to_string()));
+        continue;
+      }
+
+      for word in line.split_whitespace() {
+        if word == "..." && moves.is_empty() {
+          first = Player::O;
+        }
+
+        // move numbers end with a dot, as does the move o started without
+        if !word.ends_with('.') {
+          moves.push(word.to_string());
+        }
+      }
+    }
+
+    Ok(Record { tags, first, moves })
+  }
+}
+
+impl std::fmt::Display for Record {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    for (name, value) in &self.tags {
+      writeln!(f, "[{} \"{}\"]", name, value)?;
+    }
+
+    writeln!(f)?;
+
+    // pairs start with a move of x, o starting leaves the first one empty
+    let skipped = if self.first == Player::O { 1 } else { 0 };
+
+    for (i, m) in self.moves.iter().enumerate() {
+      let ply = i + skipped;
+
+      if i > 0 {
+        write!(f, " ")?;
+      }
+
+      if ply % 2 == 0 {
+        write!(f, "{}. ", ply / 2 + 1)?;
+      } else if i == 0 {
+        write!(f, "{}. ... ", ply / 2 + 1)?;
+      }
+
+      write!(f, "{}", m)?;
+    }
+
+    writeln!(f)
+  }
+}
+
+// today in the calendar, from the days since the epoch
+pub(crate) fn get_date() -> String {
+  let seconds = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap()
+    .as_secs();
+  // days counted from the 1st of March of year 0, so leap days end the years
+  let days = seconds / 86400 + 719468;
+  let era = days / 146097;
+  let day_of_era = days % 146097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month + 2) / 5 + 1;
+  let month = if month < 10 { month + 3 } else { month - 9 };
+  let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+  format!("{}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    grid::{self, Board},
+    random_turn::RandomTurn,
+  };
+
+  fn get_tags() -> Vec<(String, String)> {
+    vec![(String::from("Variant"), String::from("classic"))]
+  }
+
+  // the record written for the game reads back and replays to the same state
+  fn check_round_trip(mut game: Game<Board>, cells: &[grid::Cell]) -> String {
+    let start = game.state;
+
+    for cell in cells {
+      game.play(*cell);
+    }
+
+    let text = Record::new(&get_tags(), &game).to_string();
+    let record = text.parse::<Record>().unwrap();
+    let mut replayed = Game::new(game.rules.clone(), start);
+    record.replay(&mut replayed).unwrap();
+
+    assert_eq!(record.to_string(), text);
+    assert!(record.moves == Record::new(&get_tags(), &game).moves);
+    assert!(replayed.state == game.state);
+    assert!(replayed.moves == game.moves);
+    text
+  }
+
+  #[test]
+  fn record_reads_back_and_replays() {
+    let text = check_round_trip(
+      Game::new(Board::classic(), (grid::EMPTY, 0)),
+      &[4, 0, 8, 2, 1],
+    );
+
+    assert_eq!(
+      text,
+      "[Variant \"classic\"]\n[Result \"*\"]\n\n1. 5 7 2. 3 9 3. 8\n"
+    );
+  }
+
+  #[test]
+  fn record_started_by_o_skips_the_move_of_x() {
+    let game = "x..|...|... o".parse::<Game<Board>>().unwrap();
+    let text = check_round_trip(game, &[4, 8, 2]);
+
+    assert!(text.ends_with("\n1. ... 5 2. 3 9\n"));
+    assert!(text.parse::<Record>().unwrap().first == Player::O);
+  }
+
+  #[test]
+  fn illegal_move_is_reported() {
+    let record = "1. 5 5".parse::<Record>().unwrap();
+    let mut game = Game::new(Board::classic(), (grid::EMPTY, 0));
+
+    assert_eq!(
+      record.replay(&mut game),
+      Err(RecordError::InvalidMove {
+        number: 2,
+        text: String::from("5")
+      })
+    );
+  }
+
+  #[test]
+  fn record_replays_the_sides_the_coin_chose() {
+    let mut game = Game::new(RandomTurn::new(Board::classic()), (grid::EMPTY, 0, None));
+
+    for (player, cell) in [(Player::O, 4), (Player::O, 0), (Player::X, 8)] {
+      game.state = (game.state.0, game.state.1, Some(player));
+      game.play(cell);
+    }
+
+    let record = Record::new(&get_tags(), &game)
+      .to_string()
+      .parse::<Record>()
+      .unwrap();
+    let mut replayed = Game::new(RandomTurn::new(Board::classic()), (grid::EMPTY, 0, None));
+    record.replay(&mut replayed).unwrap();
+
+    assert!(record.moves == ["5o", "7o", "3x"]);
+    assert!(replayed.state == game.state);
+  }
+}
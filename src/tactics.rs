@@ -0,0 +1,124 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::Player,
+  grid::{self, Bitboard, Board, Grid},
+};
+
+pub(crate) struct Tactics {
+  // cells completing a line
+  pub(crate) wins: Bitboard,
+  // cells the opponent would complete a line with
+  pub(crate) blocks: Bitboard,
+  // cells threatening to complete two lines at once
+  pub(crate) forks: Bitboard,
+  // lines with one or two marks and none of the opponent
+  pub(crate) open_ones: u32,
+  pub(crate) open_twos: u32,
+}
+
+pub(crate) fn analyze(board: &Board, grid: Grid, player: Player) -> Tactics {
+  let (own, opponent) = match player {
+    Player::X => (grid.x, grid.o),
+    Player::O => (grid.o, grid.x),
+  };
+  let empty = grid::get_empty_bits(board, grid);
+  let k = board.k as u32;
+  let mut tactics = Tactics {
+    wins: 0,
+    blocks: 0,
+    forks: 0,
+    open_ones: 0,
+    open_twos: 0,
+  };
+  // per cell, the cells that would complete a line after marking it
+  let mut threats = [0 as Bitboard; Bitboard::BITS as usize];
+
+  for line in &board.lines {
+    let own_marks = (own & line).count_ones();
+    let opponent_marks = (opponent & line).count_ones();
+
+    if opponent_marks == 0 {
+      match own_marks {
+        1 => tactics.open_ones += 1,
+        2 => tactics.open_twos += 1,
+        _ => {}
+      }
+
+      if own_marks + 1 == k {
+        tactics.wins |= line & empty;
+      } else if own_marks + 2 == k {
+        let mut cells = line & empty;
+
+        while cells != 0 {
+          let cell = cells.trailing_zeros() as usize;
+          threats[cell] |= line & empty & !(1 << cell);
+          cells &= cells - 1;
+        }
+      }
+    } else if own_marks == 0 && opponent_marks + 1 == k {
+      tactics.blocks |= line & empty;
+    }
+  }
+
+  for (cell, threat) in threats.iter().enumerate() {
+    if threat.count_ones() > 1 {
+      tactics.forks |= 1 << cell;
+    }
+  }
+
+  tactics.forks &= !tactics.wins;
+  tactics
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get_grid(x: &[grid::Cell], o: &[grid::Cell]) -> Grid {
+    let grid = x
+      .iter()
+      .fold(grid::EMPTY, |grid, cell| grid::mark(grid, *cell, Player::X));
+    o.iter()
+      .fold(grid, |grid, cell| grid::mark(grid, *cell, Player::O))
+  }
+
+  #[test]
+  fn fork_and_block_are_found() {
+    // x o .
+    // . o .
+    // . . x
+    let tactics = analyze(&Board::classic(), get_grid(&[0, 8], &[1, 4]), Player::X);
+
+    assert_eq!(tactics.wins, 0);
+    assert_eq!(tactics.blocks, 1 << 7);
+    assert_eq!(tactics.forks, 1 << 6);
+  }
+
+  #[test]
+  fn winning_cell_is_not_a_fork() {
+    // x x .
+    // o . x
+    // x o .
+    let tactics = analyze(
+      &Board::classic(),
+      get_grid(&[0, 1, 5, 6], &[3, 7]),
+      Player::X,
+    );
+
+    assert_eq!(tactics.wins, 1 << 2);
+    assert_eq!(tactics.forks, 1 << 4 | 1 << 8);
+  }
+}
@@ -0,0 +1,237 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  ai::{self, Score},
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Bitboard, Board, Cell, Grid},
+  tactics,
+};
+
+// nine classic grids laid out like the cells of a classic grid, the cell
+// played decides the grid the opponent plays in next
+// | 0 | 1 | 2 |
+// | 3 | 4 | 5 |
+// | 6 | 7 | 8 |
+
+#[derive(Clone, Copy)]
+pub(crate) struct State {
+  grids: [Grid; 9],
+  // grids won by x or o, as cells of the large grid
+  won: Grid,
+  drawn: Bitboard,
+  // grid of the next move, any open grid when None
+  next: Option<Cell>,
+  turn: Turn,
+}
+
+pub(crate) const EMPTY: State = State {
+  grids: [grid::EMPTY; 9],
+  won: grid::EMPTY,
+  drawn: 0,
+  next: None,
+  turn: 0,
+};
+
+// grid and cell within that grid
+pub(crate) type Move = (Cell, Cell);
+
+pub(crate) struct Ultimate {
+  board: Board,
+}
+
+impl Ultimate {
+  pub(crate) fn new() -> Ultimate {
+    Ultimate {
+      board: Board::classic(),
+    }
+  }
+}
+
+fn get_closed(state: &State) -> Bitboard {
+  state.won.x | state.won.o | state.drawn
+}
+
+fn get_open(state: &State) -> Bitboard {
+  match state.next {
+    Some(next) => 1 << next,
+    None => !get_closed(state) & 0b111_111_111,
+  }
+}
+
+impl Rules for Ultimate {
+  type State = State;
+  type Move = Move;
+  type Moves<'a> = std::vec::IntoIter<Move>;
+
+  fn get_moves(&self, state: &State) -> std::vec::IntoIter<Move> {
+    let mut moves = Vec::with_capacity(81);
+
+    for b in grid::get_move_set(&self.board, get_open(state)) {
+      for cell in grid::get_empty_cells(&self.board, state.grids[b as usize]) {
+        moves.push((b, cell));
+      }
+    }
+
+    moves.into_iter()
+  }
+
+  fn apply(&self, state: &State, (b, cell): Move) -> State {
+    let player = game::get_player_from_turn(state.turn);
+    let mut next = *state;
+    let sub = grid::mark(state.grids[b as usize], cell, player);
+    next.grids[b as usize] = sub;
+
+    match grid::get_result(&self.board, sub, grid::get_turn(sub)) {
+      Result::XWin => next.won = grid::mark(next.won, b, Player::X),
+      Result::OWin => next.won = grid::mark(next.won, b, Player::O),
+      Result::Draw => next.drawn |= 1 << b,
+      Result::Continue => {}
+    }
+
+    next.next = if get_closed(&next) & 1 << cell == 0 {
+      Some(cell)
+    } else {
+      None
+    };
+    next.turn += 1;
+    next
+  }
+
+  fn get_random_move(&self, state: &State) -> Move {
+    use rand::Rng;
+
+    let grids = grid::get_move_set(&self.board, get_open(state));
+    let cells = grids
+      .map(|b| grid::get_empty_cells(&self.board, state.grids[b as usize]).len())
+      .sum();
+    let mut n = rand::thread_rng().gen_range(0..cells);
+
+    for b in grids {
+      let mut empty_cells = grid::get_empty_cells(&self.board, state.grids[b as usize]);
+
+      if n < empty_cells.len() {
+        return (b, empty_cells.nth(n).unwrap());
+      }
+
+      n -= empty_cells.len();
+    }
+
+    unreachable!()
+  }
+
+  fn get_result(&self, state: &State) -> Result {
+    let closed = get_closed(state).count_ones() as Turn;
+    grid::get_result(&self.board, state.won, closed)
+  }
+
+  fn get_player(&self, state: &State) -> Player {
+    game::get_player_from_turn(state.turn)
+  }
+
+  // won grids, lines of the large grid still open and lines inside the open
+  // grids, drawn grids block lines of both players
+  fn evaluate(&self, state: &State) -> Score {
+    let x_grid = Grid {
+      x: state.won.x,
+      o: state.won.o | state.drawn,
+    };
+    let o_grid = Grid {
+      x: state.won.x | state.drawn,
+      o: state.won.o,
+    };
+    let x = tactics::analyze(&self.board, x_grid, Player::X);
+    let o = tactics::analyze(&self.board, o_grid, Player::O);
+    let mut score = 8 * (state.won.x.count_ones() as Score - state.won.o.count_ones() as Score)
+      + 4 * (x.open_twos as Score - o.open_twos as Score);
+
+    for b in grid::get_move_set(&self.board, !get_closed(state) & 0b111_111_111) {
+      let sub = state.grids[b as usize];
+      let x = tactics::analyze(&self.board, sub, Player::X);
+      let o = tactics::analyze(&self.board, sub, Player::O);
+      score += x.open_twos as Score - o.open_twos as Score;
+    }
+
+    ai::DRAW + score.clamp(-ai::DRAW / 2, ai::DRAW / 2)
+  }
+
+  // marks of won grids are printed in capitals
+  fn print(&self, state: &State) {
+    let mut text = String::new();
+
+    for row in 0..9 {
+      if row % 3 == 0 {
+        text.push_str("\n|-----+-----+-----|");
+      }
+
+      text.push_str("\n|");
+
+      for column in 0..9 {
+        let b = row / 3 * 3 + column / 3;
+        let cell = row % 3 * 3 + column % 3;
+        let sub = state.grids[b as usize];
+        let mark = if sub.x & 1 << cell != 0 {
+          'x'
+        } else if sub.o & 1 << cell != 0 {
+          'o'
+        } else {
+          ' '
+        };
+
+        text.push(if (state.won.x | state.won.o) & 1 << b != 0 {
+          mark.to_ascii_uppercase()
+        } else {
+          mark
+        });
+        text.push(if column % 3 == 2 { '|' } else { ' ' });
+      }
+    }
+
+    text.push_str("\n|-----+-----+-----|");
+    println!("{}", text);
+
+    if self.get_result(state) == Result::Continue {
+      println!(
+        "next grid: {}",
+        match state.next {
+          Some(next) => grid::get_cell_name(&self.board, next),
+          None => String::from("any"),
+        }
+      );
+    }
+  }
+
+  fn get_move_name(&self, _state: &State, (b, cell): Move) -> String {
+    grid::get_cell_name(&self.board, b) + &grid::get_cell_name(&self.board, cell)
+  }
+
+  // grid then cell on the numpad, e.g. 73 is the bottom right cell of the top
+  // left grid, the grid can be left out when it is forced
+  fn parse_move(&self, state: &State, input: &str) -> Option<Move> {
+    if !input.is_ascii() {
+      return None;
+    }
+
+    let m = match (state.next, input.len()) {
+      (Some(next), 1) => (next, grid::get_cell_from_name(&self.board, input)?),
+      (_, 2) => (
+        grid::get_cell_from_name(&self.board, &input[..1])?,
+        grid::get_cell_from_name(&self.board, &input[1..])?,
+      ),
+      _ => return None,
+    };
+
+    self.get_moves(state).find(|legal| *legal == m)
+  }
+}
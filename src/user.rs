@@ -13,39 +13,47 @@
 // limitations under the License.
 
 use crate::{
-  game::Game,
-  grid::{self, Cell},
+  game::{Game, Rules},
+  player::{self, Action},
 };
 
-pub(crate) fn get_move(game: &Game) -> Cell {
-  let mut input = String::with_capacity(2);
+// a move, or undo or redo to take back moves or play them again
+pub(crate) fn get_action<R: Rules>(rules: &R, state: &R::State) -> Action<R::Move> {
+  let mut input = String::with_capacity(4);
 
   loop {
     std::io::stdin().read_line(&mut input).unwrap();
-    let number = input.trim().parse::<i8>().unwrap_or(0);
-
-    if number > 0 && number < 10 {
-      let cell = match number {
-        1 => Cell::BottomLeft,
-        2 => Cell::BottomMiddle,
-        3 => Cell::BottomRight,
-        4 => Cell::MiddleLeft,
-        5 => Cell::MiddleMiddle,
-        6 => Cell::MiddleRight,
-        7 => Cell::TopLeft,
-        8 => Cell::TopMiddle,
-        _ => Cell::TopRight,
-      };
-
-      if grid::is_cell_empty(game.grid, cell) {
-        return cell;
-      }
 
-      eprintln!("error: this cell is not empty");
-    } else {
-      eprintln!("error: invalid input");
+    match input.trim() {
+      "undo" => return Action::Undo,
+      "redo" => return Action::Redo,
+      text => match rules.parse_move(state, text) {
+        Some(m) => return Action::Move(m),
+        None => eprintln!("error: {}", rules.get_move_error(state, text)),
+      },
     }
 
     input.clear();
   }
 }
+
+pub(crate) struct User;
+
+impl<R: Rules> player::Player<R> for User {
+  fn get_name(&self) -> &'static str {
+    "user"
+  }
+
+  fn choose_move(&mut self, game: &Game<R>) -> R::Move {
+    loop {
+      match get_action(&game.rules, &game.state) {
+        Action::Move(m) => return m,
+        _ => eprintln!("error: no moves can be taken back here"),
+      }
+    }
+  }
+
+  fn choose_action(&mut self, game: &Game<R>) -> Action<R::Move> {
+    get_action(&game.rules, &game.state)
+  }
+}
@@ -0,0 +1,140 @@
+// Copyright 2023 Kamil Gloc
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//  http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+  game::{self, Player, Result, Rules, Turn},
+  grid::{self, Board, Cell, Grid},
+};
+
+// both players may write either symbol and whoever completes a line of x or of
+// o wins, the x and o bitboards of the grid hold symbols instead of players
+pub(crate) struct Wild {
+  board: Board,
+}
+
+// cell and symbol written in it
+pub(crate) type Move = (Cell, Player);
+
+impl Wild {
+  pub(crate) fn new(board: Board) -> Wild {
+    Wild { board }
+  }
+}
+
+pub(crate) type Moves<'a> = std::iter::FlatMap<grid::MoveSet<'a>, [Move; 2], fn(Cell) -> [Move; 2]>;
+
+fn get_symbol_moves(cell: Cell) -> [Move; 2] {
+  [(cell, Player::X), (cell, Player::O)]
+}
+
+// both symbols in every empty cell
+pub(crate) fn get_moves(board: &Board, grid: Grid) -> Moves<'_> {
+  grid::get_empty_cells(board, grid).flat_map(get_symbol_moves as fn(Cell) -> [Move; 2])
+}
+
+pub(crate) fn get_random_move(board: &Board, grid: Grid) -> Move {
+  let symbol = if rand::random() { Player::X } else { Player::O };
+  (grid::get_random_empty_cell(board, grid), symbol)
+}
+
+pub(crate) fn get_move_name(board: &Board, (cell, symbol): Move) -> String {
+  let name = if board.gravity {
+    (cell % board.width + 1).to_string()
+  } else {
+    grid::get_cell_name(board, cell)
+  };
+
+  match symbol {
+    Player::X => name + "x",
+    Player::O => name + "o",
+  }
+}
+
+// cell followed by the symbol, e.g. 5o writes o in the center of the classic
+// board, with gravity the column takes the place of the cell
+pub(crate) fn parse_move(board: &Board, grid: Grid, input: &str) -> Option<Move> {
+  if !input.is_ascii() {
+    return None;
+  }
+
+  let (name, symbol) = input.split_at(input.len().checked_sub(1)?);
+  let symbol = match symbol {
+    "x" | "X" => Player::X,
+    "o" | "O" => Player::O,
+    _ => return None,
+  };
+  let cell = if board.gravity {
+    grid::get_drop_cell(board, grid, grid::get_column_from_name(board, name)?)?
+  } else {
+    grid::get_cell_from_name(board, name)?
+  };
+
+  get_moves(board, grid).find(|legal| *legal == (cell, symbol))
+}
+
+impl Rules for Wild {
+  type State = (Grid, Turn);
+  type Move = Move;
+  type Moves<'a> = Moves<'a>;
+
+  fn get_moves<'a>(&'a self, state: &(Grid, Turn)) -> Moves<'a> {
+    get_moves(&self.board, state.0)
+  }
+
+  fn apply(&self, state: &(Grid, Turn), (cell, symbol): Move) -> (Grid, Turn) {
+    (grid::mark(state.0, cell, symbol), state.1 + 1)
+  }
+
+  fn get_random_move(&self, state: &(Grid, Turn)) -> Move {
+    get_random_move(&self.board, state.0)
+  }
+
+  // the player who made the last move completed the line
+  fn get_result(&self, state: &(Grid, Turn)) -> Result {
+    let completed = self
+      .board
+      .lines
+      .iter()
+      .any(|line| state.0.x & line == *line || state.0.o & line == *line);
+
+    if !completed {
+      return if grid::get_empty_cells(&self.board, state.0).len() == 0 {
+        Result::Draw
+      } else {
+        Result::Continue
+      };
+    }
+
+    match (game::get_player_from_turn(state.1 - 1), self.board.misere) {
+      (Player::X, false) | (Player::O, true) => Result::XWin,
+      _ => Result::OWin,
+    }
+  }
+
+  fn get_player(&self, state: &(Grid, Turn)) -> Player {
+    game::get_player_from_turn(state.1)
+  }
+
+  fn print(&self, state: &(Grid, Turn)) {
+    grid::print(&self.board, state.0);
+  }
+
+  fn get_move_name(&self, _state: &(Grid, Turn), m: Move) -> String {
+    get_move_name(&self.board, m)
+  }
+
+  fn parse_move(&self, state: &(Grid, Turn), input: &str) -> Option<Move> {
+    parse_move(&self.board, state.0, input)
+  }
+}